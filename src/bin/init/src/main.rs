@@ -70,8 +70,16 @@ fn initialize_namer(bootstrap: ObjID) {
     let kernel_init_info = get_kernel_init_info();
     let _ = handle.remove("/initrd", true);
     let _ = handle.put_namespace("/initrd");
-    for name in kernel_init_info.names() {
-        let _ = handle.put(&format!("/initrd/{}", name.name()), name.id().raw());
+    let names: Vec<_> = kernel_init_info
+        .names()
+        .iter()
+        .map(|name| (format!("/initrd/{}", name.name()), name.id().raw()))
+        .collect();
+    let batch: Vec<_> = names.iter().map(|(n, id)| (n.as_str(), *id)).collect();
+    if let Err(failures) = handle.put_many(&batch) {
+        for (name, e) in failures {
+            tracing::warn!("failed to add {} to naming: {}", name, e);
+        }
     }
 
     tracing::info!("naming ready");
@@ -1,6 +1,12 @@
 #![allow(dead_code)]
 
-use naming_core::{Entry, EntryType, ErrorKind, NameStore};
+use std::{
+    ffi::OsStr,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+};
+
+use naming_core::{Entry, EntryType, ErrorKind, NameStore, PutFailure};
 
 fn test_single_put_then_get() {
     println!("doing test_single_put_then_get");
@@ -34,6 +40,43 @@ fn test_multi_put_then_get() {
     }
 }
 
+fn put_many_mixed() {
+    println!("doing put_many_mixed");
+
+    let store = NameStore::new();
+    let session = store.root_session();
+
+    // The two failing entries have the same name, so only their indices tell them apart. The
+    // second "a" overwrites the first rather than failing.
+    let entries = [
+        ("a", EntryType::Object(1)),
+        ("missing/b", EntryType::Object(2)),
+        ("ns", EntryType::Namespace),
+        ("missing/b", EntryType::Object(3)),
+        ("ns/c", EntryType::Object(4)),
+        ("a", EntryType::Object(5)),
+    ];
+    assert_eq!(
+        session.put_many(&entries),
+        Err(vec![
+            PutFailure {
+                index: 1,
+                error: ErrorKind::NotFound
+            },
+            PutFailure {
+                index: 3,
+                error: ErrorKind::NotFound
+            },
+        ])
+    );
+    assert_eq!(session.get("a"), Entry::try_new("a", EntryType::Object(5)));
+    assert_eq!(
+        session.get("ns/c"),
+        Entry::try_new("c", EntryType::Object(4))
+    );
+    assert_eq!(session.get("missing/b"), Err(ErrorKind::NotFound));
+}
+
 fn put_namespace() {
     println!("doing put_namespace");
 
@@ -354,9 +397,385 @@ fn load_from_object() {
     }
 }
 
+fn flush_then_reload() {
+    println!("doing flush_then_reload");
+
+    let id = {
+        let store = NameStore::new();
+        let session = store.root_session();
+        session.put("ns", EntryType::Namespace).unwrap();
+        session.put("ns/a", EntryType::Object(7)).unwrap();
+        assert_eq!(session.is_persistent("ns/a"), Ok(true));
+        assert_eq!(session.is_persistent("missing"), Err(ErrorKind::NotFound));
+        assert_eq!(session.flush(), Ok(()));
+        store.id()
+    };
+
+    {
+        let store = NameStore::new_in(id).expect("NameStore should have loaded properly");
+        let session = store.root_session();
+        assert_eq!(
+            session.get("ns/a"),
+            Entry::try_new("a", EntryType::Object(7))
+        );
+    }
+}
+
+fn non_utf8_name() {
+    println!("doing non_utf8_name");
+
+    let store = NameStore::new();
+    let session = store.root_session();
+
+    // "caf\xe9" is Latin-1, and not valid UTF-8.
+    let name = unsafe { OsStr::from_encoded_bytes_unchecked(b"caf\xe9") };
+    let nested = Path::new("ns").join(name);
+    assert_eq!(session.put("ns", EntryType::Namespace), Ok(()));
+    assert_eq!(session.put(name, EntryType::Object(1)), Ok(()));
+    assert_eq!(session.put(&nested, EntryType::Object(2)), Ok(()));
+
+    let entry = session.get(name).unwrap();
+    assert_eq!(entry.name.as_bytes(), b"caf\xe9");
+    assert_eq!(entry.name.to_str(), None);
+    assert_eq!(entry.entry_type, EntryType::Object(1));
+    assert_eq!(
+        session.get(&nested),
+        Entry::try_new(name, EntryType::Object(2))
+    );
+    // A lossy conversion of the name must not find it.
+    assert_eq!(
+        session.get(entry.name.to_string_lossy().as_ref()),
+        Err(ErrorKind::NotFound)
+    );
+
+    assert_eq!(session.remove(name, false), Ok(()));
+    assert_eq!(session.get(name), Err(ErrorKind::NotFound));
+}
+
+fn enumerate_snapshot() {
+    println!("doing enumerate_snapshot");
+
+    let store = NameStore::new();
+    let session = store.root_session();
+    let other = store.root_session();
+    assert_eq!(session.put("big", EntryType::Namespace), Ok(()));
+    for i in 0..500 {
+        assert_eq!(
+            session.put(format!("big/{}", i), EntryType::Object(i)),
+            Ok(())
+        );
+    }
+
+    let snapshot = session.enumerate_snapshot("big").unwrap();
+    assert_eq!(snapshot.len(), 500);
+
+    // The store must not be locked while the snapshot is alive, or these would never return.
+    assert_eq!(other.put("big/new", EntryType::Object(500)), Ok(()));
+    assert_eq!(
+        other.get("big/new"),
+        Entry::try_new("new", EntryType::Object(500))
+    );
+
+    // Paging sees the namespace as it was when the snapshot was taken.
+    let mut paged = Vec::new();
+    let mut offset = 0;
+    while offset < snapshot.len() {
+        let page = snapshot.page(offset, 64);
+        paged.extend_from_slice(page);
+        offset += page.len();
+    }
+    assert_eq!(paged.len(), 500);
+    assert!(paged.iter().all(|e| e.name.as_bytes() != b"new"));
+    assert!(snapshot.page(500, 64).is_empty());
+
+    assert_eq!(session.enumerate_snapshot("big").unwrap().len(), 501);
+}
+
+fn mount() {
+    println!("doing mount");
+
+    let other = NameStore::new();
+    let other_session = other.root_session();
+    assert_eq!(other_session.put("sub", EntryType::Namespace), Ok(()));
+    assert_eq!(other_session.put("sub/deep", EntryType::Object(2)), Ok(()));
+
+    let store = NameStore::new();
+    let mut session = store.root_session();
+    assert_eq!(session.put("mnt", EntryType::Namespace), Ok(()));
+    assert_eq!(session.put("mnt/hidden", EntryType::Object(1)), Ok(()));
+    assert_eq!(session.put("file", EntryType::Object(3)), Ok(()));
+
+    assert_eq!(
+        session.mount("file", other.id()),
+        Err(ErrorKind::NotNamespace)
+    );
+    assert_eq!(session.mount("mnt", other.id()), Ok(()));
+    assert_eq!(
+        session.mount("mnt", other.id()),
+        Err(ErrorKind::InvalidName)
+    );
+
+    // Lookups that cross the mount point are served by the mounted store.
+    assert_eq!(
+        session.get("/mnt/sub/deep"),
+        Entry::try_new("deep", EntryType::Object(2))
+    );
+    assert_eq!(session.get("mnt/hidden"), Err(ErrorKind::NotFound));
+    let names: Vec<_> = session
+        .enumerate_namespace("mnt")
+        .unwrap()
+        .iter()
+        .map(|e| e.name.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, ["sub"]);
+
+    // Writes below the mount point land in the mounted store.
+    assert_eq!(session.put("mnt/sub/new", EntryType::Object(4)), Ok(()));
+    assert_eq!(
+        other_session.get("/sub/new"),
+        Entry::try_new("new", EntryType::Object(4))
+    );
+
+    // A second mount of the same store shares its entries with the first mount, and with other.
+    assert_eq!(session.put("mnt2", EntryType::Namespace), Ok(()));
+    assert_eq!(session.mount("mnt2", other.id()), Ok(()));
+    assert_eq!(session.put("mnt2/sub/shared", EntryType::Object(5)), Ok(()));
+    assert_eq!(
+        other_session.put("/sub/theirs", EntryType::Object(6)),
+        Ok(())
+    );
+    assert_eq!(
+        session.get("mnt/sub/shared"),
+        Entry::try_new("shared", EntryType::Object(5))
+    );
+    assert_eq!(
+        session.get("mnt2/sub/theirs"),
+        Entry::try_new("theirs", EntryType::Object(6))
+    );
+    assert_eq!(session.unmount("mnt2"), Ok(()));
+
+    // Changing into the mount keeps paths relative to the outer tree.
+    assert_eq!(session.change_namespace("mnt/sub"), Ok(()));
+    assert_eq!(session.cwd(), PathBuf::from("/mnt/sub"));
+    assert_eq!(
+        session.get("deep"),
+        Entry::try_new("deep", EntryType::Object(2))
+    );
+    assert_eq!(
+        session.get("../../file"),
+        Entry::try_new("file", EntryType::Object(3))
+    );
+    assert_eq!(session.change_namespace("/"), Ok(()));
+
+    assert_eq!(session.remove("mnt", true), Err(ErrorKind::InvalidName));
+    assert_eq!(session.unmount("mnt"), Ok(()));
+    assert_eq!(session.unmount("mnt"), Err(ErrorKind::NotFound));
+    assert_eq!(
+        session.get("mnt/hidden"),
+        Entry::try_new("hidden", EntryType::Object(1))
+    );
+}
+
+fn symlink_and_link() {
+    println!("doing symlink_and_link");
+
+    let store = NameStore::new();
+    let session = store.root_session();
+    assert_eq!(session.put("a", EntryType::Namespace), Ok(()));
+    assert_eq!(session.put("a/target", EntryType::Object(1)), Ok(()));
+    assert_eq!(session.symlink("sym", "/a/target"), Ok(()));
+    assert_eq!(session.link("alias", "sym"), Ok(()));
+    assert_eq!(session.symlink("a/rel", "target"), Ok(()));
+    assert_eq!(session.symlink("dir", "a"), Ok(()));
+
+    // Links are followed on lookup, and their kind shows in enumeration.
+    assert_eq!(
+        session.get("sym"),
+        Entry::try_new("target", EntryType::Object(1))
+    );
+    assert_eq!(
+        session.get("a/rel"),
+        Entry::try_new("target", EntryType::Object(1))
+    );
+    assert_eq!(
+        session.get("dir/target"),
+        Entry::try_new("target", EntryType::Object(1))
+    );
+    assert_eq!(
+        session.get("alias"),
+        Entry::try_new("alias", EntryType::Object(1))
+    );
+    let sym = session
+        .enumerate_namespace("/")
+        .unwrap()
+        .into_iter()
+        .find(|e| e.name.as_path() == Path::new("sym"))
+        .unwrap();
+    assert!(
+        matches!(sym.entry_type, EntryType::Symlink(t) if t.as_path() == Path::new("/a/target"))
+    );
+    // Without following, the link itself comes back, but links earlier in the path are followed.
+    assert!(matches!(
+        session.get_no_follow("sym").map(|e| e.entry_type),
+        Ok(EntryType::Symlink(t)) if t.as_path() == Path::new("/a/target")
+    ));
+    assert_eq!(
+        session.get_no_follow("dir/target"),
+        Entry::try_new("target", EntryType::Object(1))
+    );
+
+    // Move the target away and put something else in its place. The symbolic link follows the
+    // path, while the alias still names the original object.
+    assert_eq!(session.remove("a/target", false), Ok(()));
+    assert_eq!(session.get("sym"), Err(ErrorKind::NotFound));
+    assert_eq!(session.put("moved", EntryType::Object(1)), Ok(()));
+    assert_eq!(session.put("a/target", EntryType::Object(2)), Ok(()));
+    assert_eq!(
+        session.get("sym"),
+        Entry::try_new("target", EntryType::Object(2))
+    );
+    assert_eq!(
+        session.get("alias"),
+        Entry::try_new("alias", EntryType::Object(1))
+    );
+
+    // Removing a link removes the link, not its target.
+    assert_eq!(session.remove("sym", false), Ok(()));
+    assert_eq!(session.get("sym"), Err(ErrorKind::NotFound));
+    assert_eq!(
+        session.get("a/target"),
+        Entry::try_new("target", EntryType::Object(2))
+    );
+
+    assert_eq!(session.symlink("loop1", "loop2"), Ok(()));
+    assert_eq!(session.symlink("loop2", "loop1"), Ok(()));
+    assert_eq!(session.get("loop1"), Err(ErrorKind::LinkLoop));
+    assert_eq!(session.link("alias2", "dir"), Err(ErrorKind::NotFile));
+}
+
+fn for_each_entry() {
+    println!("doing for_each_entry");
+
+    let store = NameStore::new();
+    let session = store.root_session();
+    assert_eq!(session.put("ns", EntryType::Namespace), Ok(()));
+    for i in 0..10 {
+        assert_eq!(
+            session.put(format!("ns/{}", i), EntryType::Object(i)),
+            Ok(())
+        );
+    }
+
+    // Stop at the target, so the entries after it are never visited.
+    let mut visited = Vec::new();
+    assert_eq!(
+        session.for_each_entry("ns", |entry| {
+            visited.push(entry.entry_type);
+            if entry.name.as_path() == Path::new("3") {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }),
+        Ok(())
+    );
+    assert_eq!(visited.last(), Some(&EntryType::Object(3)));
+    assert_eq!(visited.len(), 4);
+
+    let mut count = 0;
+    assert_eq!(
+        session.for_each_entry("ns", |_| {
+            count += 1;
+            ControlFlow::Continue(())
+        }),
+        Ok(())
+    );
+    assert_eq!(count, 10);
+    assert_eq!(
+        session.for_each_entry("ns/0", |_| ControlFlow::Continue(())),
+        Err(ErrorKind::NotNamespace)
+    );
+}
+
+fn enumerate_paged() {
+    println!("doing enumerate_paged");
+
+    let store = NameStore::new();
+    let session = store.root_session();
+    assert_eq!(session.put("ns", EntryType::Namespace), Ok(()));
+    for i in 0..7 {
+        assert_eq!(
+            session.put(format!("ns/{}", i), EntryType::Object(i)),
+            Ok(())
+        );
+    }
+
+    // Page through the namespace two entries at a time and put the pages back together.
+    let mut pages = Vec::new();
+    let mut offset = 0;
+    loop {
+        let (page, total) = session.enumerate_namespace_range("ns", offset, 2).unwrap();
+        assert_eq!(total, 7);
+        assert!(page.len() <= 2);
+        if page.is_empty() {
+            break;
+        }
+        offset += page.len();
+        pages.extend(page);
+    }
+    assert_eq!(offset, 7);
+    assert_eq!(pages, session.enumerate_namespace("ns").unwrap());
+}
+
+fn relative_paths() {
+    println!("doing relative_paths");
+
+    let store = NameStore::new();
+    let mut session = store.root_session();
+    assert_eq!(session.cwd(), PathBuf::from("/"));
+
+    assert_eq!(session.put("foo", EntryType::Object(1)), Ok(()));
+    assert_eq!(session.put("ns", EntryType::Namespace), Ok(()));
+    assert_eq!(session.put("ns/foo", EntryType::Object(2)), Ok(()));
+
+    assert_eq!(session.change_namespace("ns"), Ok(()));
+    assert_eq!(session.cwd(), PathBuf::from("/ns"));
+
+    // Relative paths resolve under the working namespace, absolute ones at the root.
+    assert_eq!(
+        session.get("foo"),
+        Entry::try_new("foo", EntryType::Object(2))
+    );
+    assert_eq!(
+        session.get("/foo"),
+        Entry::try_new("foo", EntryType::Object(1))
+    );
+
+    assert_eq!(session.put("bar", EntryType::Object(3)), Ok(()));
+    assert_eq!(
+        session.get("/ns/bar"),
+        Entry::try_new("bar", EntryType::Object(3))
+    );
+    assert_eq!(session.get("/bar"), Err(ErrorKind::NotFound));
+
+    assert_eq!(session.link("baz", "/foo"), Ok(()));
+    assert_eq!(
+        session.get("/ns/baz"),
+        Entry::try_new("baz", EntryType::Object(1))
+    );
+
+    assert_eq!(session.remove("foo", false), Ok(()));
+    assert_eq!(session.get("/ns/foo"), Err(ErrorKind::NotFound));
+    assert_eq!(
+        session.get("/foo"),
+        Entry::try_new("foo", EntryType::Object(1))
+    );
+}
+
 fn main() {
     test_single_put_then_get();
     test_multi_put_then_get();
+    put_many_mixed();
     put_namespace();
     namespace_nested();
     traverse_namespace_nested_1();
@@ -365,4 +784,12 @@ fn main() {
     remove_nested();
     remove_recursive();
     load_from_object();
+    flush_then_reload();
+    non_utf8_name();
+    enumerate_snapshot();
+    mount();
+    symlink_and_link();
+    for_each_entry();
+    relative_paths();
+    enumerate_paged();
 }
@@ -17,7 +17,9 @@ use twizzler_abi::syscall::{
     ThreadSync, ThreadSyncFlags, ThreadSyncOp, ThreadSyncReference, ThreadSyncSleep, ThreadSyncWake,
 };
 use twizzler_async::{Async, AsyncSetup, Task};
-use twizzler_queue_raw::{QueueEntry, RawQueue, RawQueueHdr, ReceiveFlags, SubmissionFlags};
+use twizzler_queue_raw::{
+    entry_stride, QueueEntry, RawQueue, RawQueueHdr, ReceiveFlags, SubmissionFlags,
+};
 
 async fn get7() -> i32 {
     println!("hello from async");
@@ -68,7 +70,7 @@ impl<T: Copy + Default> AsyncSetup for Queue<T> {
 
 fn it_transmits() {
     println!("queue test");
-    let qh = RawQueueHdr::new(4, std::mem::size_of::<QueueEntry<u32>>());
+    let qh = RawQueueHdr::new(4, entry_stride::<i32>());
     let mut buffer = [QueueEntry::<i32>::default(); 1 << 4];
     let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
 
@@ -119,6 +119,20 @@ impl SchedulingQueues {
         false
     }
 
+    /// Remove and return the highest priority thread that is not pinned to a CPU, if any.
+    pub fn take_unpinned(&mut self) -> Option<ThreadRef> {
+        for queue in &mut self.queues {
+            let mut cursor = queue.front_mut();
+            while let Some(item) = cursor.get() {
+                if item.affinity.load(Ordering::SeqCst) < 0 {
+                    return cursor.remove();
+                }
+                cursor.move_next();
+            }
+        }
+        None
+    }
+
     pub fn get_min_non_empty(&self) -> usize {
         for i in 0..NR_QUEUES {
             if !self.queues[i].is_empty() {
@@ -223,6 +237,15 @@ impl Processor {
         self.load.load(Ordering::SeqCst)
     }
 
+    /// The index of this processor's top-level node in the CPU topology, or 0 if the topology is
+    /// not yet known.
+    pub fn cluster(&self) -> usize {
+        self.topology_path
+            .poll()
+            .and_then(|path| path.first())
+            .map_or(0, |(idx, _)| *idx)
+    }
+
     fn set_topology(&self, topo_path: Vec<(usize, bool)>) {
         self.topology_path.call_once(|| topo_path);
     }
@@ -232,7 +255,7 @@ impl Processor {
             .store(true, core::sync::atomic::Ordering::SeqCst);
     }
 
-    fn is_running(&self) -> bool {
+    pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
     }
 
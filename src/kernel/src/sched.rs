@@ -2,7 +2,7 @@ use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
 
 use fixedbitset::FixedBitSet;
-use twizzler_abi::thread::ExecutionState;
+use twizzler_abi::{object::ObjID, thread::ExecutionState};
 
 use crate::{
     clock::Nanoseconds,
@@ -170,12 +170,11 @@ fn schedule_thread_on_cpu(thread: ThreadRef, processor: &Processor) {
 
 fn take_a_thread_from_cpu(processor: &Processor) -> Option<ThreadRef> {
     let mut sched = processor.schedlock();
-    let thread = sched.choose_next(false);
-    if let Some(ref thread) = thread {
-        thread.current_processor_queue.store(-1, Ordering::SeqCst);
-        processor.load.fetch_sub(1, Ordering::SeqCst);
-    }
-    thread
+    /* pinned threads never migrate, but unpinned ones queued behind them may */
+    let thread = sched.take_unpinned()?;
+    thread.current_processor_queue.store(-1, Ordering::SeqCst);
+    processor.load.fetch_sub(1, Ordering::SeqCst);
+    Some(thread)
 }
 
 const STEAL_LOAD_THRESH: u64 = 3;
@@ -228,7 +227,11 @@ fn balance(topo: &CPUTopoNode) {
 }
 
 fn select_cpu(thread: &ThreadRef) -> u32 {
-    /* TODO: restrict via cpu sets as step 0, and in global searches */
+    /* 0: threads pinned to a CPU always go there */
+    let affinity = thread.affinity.load(Ordering::Acquire);
+    if affinity >= 0 {
+        return affinity as u32;
+    }
     /* TODO: take SMT into acount */
     let last_cpuid = thread.last_cpu.load(Ordering::Acquire);
     /* 1: if the thread can run on the last CPU it ran on, and that CPU is idle, then do that. */
@@ -266,6 +269,15 @@ pub fn remove_thread(id: u64) {
     ALL_THREADS.lock().remove(&id);
 }
 
+/// Find a live thread by the ID of its repr object.
+pub fn lookup_thread_by_repr(id: ObjID) -> Option<ThreadRef> {
+    ALL_THREADS
+        .lock()
+        .values()
+        .find(|t| t.objid() == id)
+        .cloned()
+}
+
 pub fn schedule_new_thread(thread: Thread) -> ThreadRef {
     thread.set_state(ExecutionState::Running);
     let thread = Arc::new(thread);
@@ -499,6 +511,7 @@ pub fn schedule_stattick(dt: Nanoseconds) {
             } else {
                 cur.stats.sys.fetch_add(1, Ordering::SeqCst);
             }
+            cur.stats.cpu_time.fetch_add(dt, Ordering::SeqCst);
 
             //TODO user vs sys
             let diff = cur.stats.last.load(Ordering::SeqCst);
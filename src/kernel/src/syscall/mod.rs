@@ -5,7 +5,7 @@ use twizzler_abi::{
     kso::{KactionCmd, KactionError, KactionValue},
     object::{ObjID, Protections},
     syscall::{
-        ClockFlags, ClockInfo, ClockKind, ClockSource, FemtoSeconds, GetRandomError,
+        ClockFlags, ClockInfo, ClockKind, ClockSource, CpuInfo, FemtoSeconds, GetRandomError,
         GetRandomFlags, HandleType, KernelConsoleReadSource, ObjectCreateError, ObjectMapError,
         ObjectReadMapError, ReadClockInfoError, ReadClockListError, ReadClockListFlags, SysInfo,
         Syscall, ThreadSpawnError, ThreadSyncError,
@@ -19,6 +19,7 @@ use self::{
 use crate::{
     clock::{fill_with_every_first, fill_with_first_kind, fill_with_kind},
     memory::VirtAddr,
+    processor::all_processors,
     random::getrandom,
     time::TICK_SOURCES,
 };
@@ -85,13 +86,33 @@ fn type_sys_thread_sync(ptr: u64, len: u64, timeoutptr: u64) -> Result<usize, Th
 }
 
 fn write_sysinfo(info: &mut SysInfo) {
-    // TODO
-    info.cpu_count = 1;
+    info.cpu_count = all_processors()
+        .iter()
+        .flatten()
+        .filter(|p| p.is_running())
+        .count();
     info.flags = 0;
     info.version = 1;
     info.page_size = 0x1000;
 }
 
+// Fill in as many CPUs as fit, and return the total number of running CPUs.
+fn type_sys_cpu_info(ptr: u64, len: u64) -> Option<usize> {
+    let cpus: &mut [CpuInfo] = unsafe { create_user_slice(ptr, len) }?;
+    let running = all_processors().iter().flatten().filter(|p| p.is_running());
+    let mut count = 0;
+    for p in running {
+        if let Some(info) = cpus.get_mut(count) {
+            *info = CpuInfo {
+                id: p.id,
+                cluster: p.cluster() as u32,
+            };
+        }
+        count += 1;
+    }
+    Some(count)
+}
+
 fn type_sys_kaction(
     cmd: u64,
     hi: u64,
@@ -411,7 +432,8 @@ pub fn syscall_entry<T: SyscallContext>(context: &mut T) {
             }
         }
         Syscall::ThreadCtrl => {
-            let [code, val] = thread_ctrl(context.arg0::<u64>().into(), context.arg1());
+            let target = ObjID::from_parts([context.arg2(), context.arg3()]);
+            let [code, val] = thread_ctrl(context.arg0::<u64>().into(), context.arg1(), target);
             context.set_return_values(code, val);
             return;
         }
@@ -436,6 +458,10 @@ pub fn syscall_entry<T: SyscallContext>(context: &mut T) {
             let (code, val) = convert_result_to_codes(result, zero_ok, one_err);
             context.set_return_values(code, val);
         }
+        Syscall::CpuInfo => match type_sys_cpu_info(context.arg0(), context.arg1()) {
+            Some(count) => context.set_return_values(0u64, count as u64),
+            None => context.set_return_values(1u64, 0u64),
+        },
         Syscall::ReadClockList => {
             let result = type_read_clock_list(
                 context.arg0(),
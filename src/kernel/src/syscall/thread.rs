@@ -1,16 +1,110 @@
 use twizzler_abi::{
     object::ObjID,
-    syscall::{ThreadControl, ThreadSpawnArgs, ThreadSpawnError},
+    syscall::{ThreadControl, ThreadPriority, ThreadSpawnArgs, ThreadSpawnError},
     upcall::{UpcallFrame, UpcallTarget},
 };
 
-use crate::thread::current_thread_ref;
+use crate::{
+    processor::all_processors,
+    sched::lookup_thread_by_repr,
+    thread::{current_thread_ref, ThreadRef},
+};
 
 pub fn sys_spawn(args: &ThreadSpawnArgs) -> Result<ObjID, ThreadSpawnError> {
     crate::thread::entry::start_new_user(*args)
 }
 
-pub fn thread_ctrl(cmd: ThreadControl, arg: u64) -> [u64; 2] {
+fn lookup_target(target: ObjID) -> Result<ThreadRef, ThreadSpawnError> {
+    if target.raw() == 0 {
+        return Ok(current_thread_ref().unwrap());
+    }
+    lookup_thread_by_repr(target).ok_or(ThreadSpawnError::NotFound)
+}
+
+// Look up a thread that the caller wants to change. Threads may only change threads that share
+// their address space.
+fn lookup_target_mut(target: ObjID) -> Result<ThreadRef, ThreadSpawnError> {
+    let thread = lookup_target(target)?;
+    let current = current_thread_ref().unwrap();
+    if !thread.shares_memory_context(&current) {
+        return Err(ThreadSpawnError::PermissionDenied);
+    }
+    Ok(thread)
+}
+
+fn set_affinity(target: ObjID, cpu: u64) -> Result<u64, ThreadSpawnError> {
+    let thread = lookup_target_mut(target)?;
+    let affinity = if cpu == u64::MAX {
+        -1
+    } else {
+        // A thread pinned to a CPU that is not running would never be scheduled again.
+        let running = all_processors()
+            .get(cpu as usize)
+            .is_some_and(|p| p.as_ref().is_some_and(|p| p.is_running()));
+        if !running {
+            return Err(ThreadSpawnError::InvalidArgument);
+        }
+        cpu as i32
+    };
+    thread
+        .affinity
+        .store(affinity, core::sync::atomic::Ordering::SeqCst);
+    // A thread waiting on another CPU's queue will move when it is next scheduled.
+    Ok(0)
+}
+
+fn get_affinity(target: ObjID) -> Result<u64, ThreadSpawnError> {
+    let thread = lookup_target(target)?;
+    let affinity = thread.affinity.load(core::sync::atomic::Ordering::SeqCst);
+    Ok(if affinity < 0 {
+        u64::MAX
+    } else {
+        affinity as u64
+    })
+}
+
+fn set_priority(target: ObjID, prio: u64) -> Result<u64, ThreadSpawnError> {
+    let thread = lookup_target_mut(target)?;
+    let prio = ThreadPriority::try_from(prio).map_err(|_| ThreadSpawnError::InvalidArgument)?;
+    // There is no privilege to grant raised priorities yet, so a thread may only set priorities up
+    // to the default or its own, whichever is higher.
+    let limit = core::cmp::max(
+        current_thread_ref().unwrap().base_priority(),
+        ThreadPriority::default(),
+    );
+    if prio > limit {
+        return Err(ThreadSpawnError::PermissionDenied);
+    }
+    thread.set_base_priority(prio);
+    Ok(0)
+}
+
+fn get_priority(target: ObjID) -> Result<u64, ThreadSpawnError> {
+    Ok(lookup_target(target)?.base_priority().into())
+}
+
+fn get_cpu_time(target: ObjID) -> Result<u64, ThreadSpawnError> {
+    Ok(lookup_target(target)?
+        .stats
+        .cpu_time
+        .load(core::sync::atomic::Ordering::SeqCst))
+}
+
+pub fn thread_ctrl(cmd: ThreadControl, arg: u64, target: ObjID) -> [u64; 2] {
+    let res = match cmd {
+        ThreadControl::SetAffinity => Some(set_affinity(target, arg)),
+        ThreadControl::GetAffinity => Some(get_affinity(target)),
+        ThreadControl::SetPriority => Some(set_priority(target, arg)),
+        ThreadControl::GetPriority => Some(get_priority(target)),
+        ThreadControl::GetCpuTime => Some(get_cpu_time(target)),
+        _ => None,
+    };
+    if let Some(res) = res {
+        return match res {
+            Ok(val) => [0, val],
+            Err(e) => [1, e as u64],
+        };
+    }
     match cmd {
         ThreadControl::SetUpcall => {
             let Some(data) = (unsafe { (arg as usize as *const UpcallTarget).as_ref() }) else {
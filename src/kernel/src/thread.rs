@@ -41,6 +41,8 @@ pub struct ThreadStats {
     pub sys: AtomicU64,
     pub idle: AtomicU64,
     pub last: AtomicU64,
+    /// Nanoseconds of CPU time used since the thread started.
+    pub cpu_time: AtomicU64,
 }
 
 pub struct Thread {
@@ -100,6 +102,16 @@ pub fn current_memory_context() -> Option<ContextRef> {
 }
 
 impl Thread {
+    /// Whether this thread runs in the same memory context as `other`. Kernel threads, which have
+    /// no memory context, only share with each other.
+    pub fn shares_memory_context(&self, other: &Thread) -> bool {
+        match (&self.memory_context, &other.memory_context) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
     pub fn new(
         ctx: Option<ContextRef>,
         spawn_args: Option<ThreadSpawnArgs>,
@@ -138,7 +150,7 @@ impl Thread {
     pub fn new_idle() -> Self {
         let mut thread = Self::new(None, None, Priority::default_idle());
         thread.flags.fetch_or(THREAD_PROC_IDLE, Ordering::SeqCst);
-        thread.priority.class = PriorityClass::Idle;
+        thread.priority.set(PriorityClass::Idle, 0);
         thread.switch_lock.store(1, Ordering::SeqCst);
         thread
     }
@@ -1,26 +1,57 @@
-use core::sync::atomic::{AtomicI32, Ordering};
+use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+
+use twizzler_abi::syscall::ThreadPriority;
 
 use super::{current_thread_ref, flags::THREAD_HAS_DONATED_PRIORITY, Thread};
 /// [`Thread`]s are triggered based on their priority, which is their [`PriorityClass`] coupled
 /// with their adjustment number. Their
 /// [`  PriorityClass`]
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct Priority {
-    pub(super) class: PriorityClass,
+    // Stored as a u32 so that a running thread's class can be changed in place.
+    class: AtomicU32,
     pub(super) adjust: AtomicI32,
 }
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::new(PriorityClass::default(), 0)
+    }
+}
+
 impl Priority {
     #[allow(clippy::declare_interior_mutable_const)]
-    pub const REALTIME: Self = Self {
-        class: PriorityClass::RealTime,
-        adjust: AtomicI32::new(0),
-    };
+    pub const REALTIME: Self = Self::new(PriorityClass::RealTime, 0);
+
+    pub(super) const fn new(class: PriorityClass, adjust: i32) -> Self {
+        Self {
+            class: AtomicU32::new(class as u32),
+            adjust: AtomicI32::new(adjust),
+        }
+    }
+
+    pub(super) fn class(&self) -> PriorityClass {
+        match self.class.load(Ordering::SeqCst) {
+            0 => PriorityClass::RealTime,
+            1 => PriorityClass::User,
+            2 => PriorityClass::Background,
+            _ => PriorityClass::Idle,
+        }
+    }
+
+    /// Change the class and adjustment of this priority. Callers are responsible for
+    /// rescheduling if the change affects which thread should be running.
+    pub(super) fn set(&self, class: PriorityClass, adjust: i32) {
+        self.class.store(class as u32, Ordering::SeqCst);
+        self.adjust.store(adjust, Ordering::SeqCst);
+    }
+
     pub fn queue_number<const NR_QUEUES: usize>(&self) -> usize {
         assert_eq!(NR_QUEUES % PriorityClass::ClassCount as usize, 0);
         let queues_per_class = NR_QUEUES / PriorityClass::ClassCount as usize;
         assert!(queues_per_class > 0 && queues_per_class % 2 == 0);
         let equilibrium = (queues_per_class / 2) as i32;
-        let base_queue = self.class as usize * queues_per_class + equilibrium as usize;
+        let base_queue = self.class() as usize * queues_per_class + equilibrium as usize;
         let adj = self
             .adjust
             .load(Ordering::SeqCst)
@@ -32,10 +63,7 @@ impl Priority {
 
     pub fn from_queue_number<const NR_QUEUES: usize>(queue: usize) -> Self {
         if queue == NR_QUEUES {
-            return Self {
-                class: PriorityClass::Idle,
-                adjust: AtomicI32::new(i32::MAX),
-            };
+            return Self::new(PriorityClass::Idle, i32::MAX);
         }
         let queues_per_class = NR_QUEUES / PriorityClass::ClassCount as usize;
         let class = queue / queues_per_class;
@@ -44,37 +72,25 @@ impl Priority {
         let base_queue = class * queues_per_class + equilibrium;
         let adj = queue as i32 - base_queue as i32;
         Self {
-            class: unsafe { core::intrinsics::transmute(class as u32) },
+            class: AtomicU32::new(class as u32),
             adjust: AtomicI32::new(adj),
         }
     }
 
     pub fn default_user() -> Self {
-        Self {
-            class: PriorityClass::User,
-            adjust: Default::default(),
-        }
+        Self::new(PriorityClass::User, 0)
     }
 
     pub fn default_realtime() -> Self {
-        Self {
-            class: PriorityClass::RealTime,
-            adjust: Default::default(),
-        }
+        Self::new(PriorityClass::RealTime, 0)
     }
 
     pub fn default_idle() -> Self {
-        Self {
-            class: PriorityClass::Idle,
-            adjust: Default::default(),
-        }
+        Self::new(PriorityClass::Idle, 0)
     }
 
     pub fn default_background() -> Self {
-        Self {
-            class: PriorityClass::Background,
-            adjust: Default::default(),
-        }
+        Self::new(PriorityClass::Background, 0)
     }
 }
 
@@ -95,7 +111,7 @@ pub(super) enum PriorityClass {
 
 impl PartialEq for Priority {
     fn eq(&self, other: &Self) -> bool {
-        self.class == other.class
+        self.class() == other.class()
             && self.adjust.load(Ordering::Relaxed) == other.adjust.load(Ordering::Relaxed)
     }
 }
@@ -109,7 +125,7 @@ impl PartialOrd for PriorityClass {
 
 impl PartialOrd for Priority {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
-        match self.class.partial_cmp(&other.class) {
+        match self.class().partial_cmp(&other.class()) {
             Some(core::cmp::Ordering::Equal) => {}
             ord => return ord,
         }
@@ -122,10 +138,7 @@ impl PartialOrd for Priority {
 
 impl Clone for Priority {
     fn clone(&self) -> Self {
-        Self {
-            class: self.class,
-            adjust: AtomicI32::new(self.adjust.load(Ordering::SeqCst)),
-        }
+        Self::new(self.class(), self.adjust.load(Ordering::SeqCst))
     }
 }
 
@@ -196,4 +209,31 @@ impl Thread {
     pub fn queue_number<const NR_QUEUES: usize>(&self) -> usize {
         self.priority.queue_number::<NR_QUEUES>()
     }
+
+    /// Change this thread's base priority, moving it to the right run queue if it is waiting on
+    /// one.
+    pub fn set_base_priority(&self, prio: ThreadPriority) {
+        let (class, adjust) = match prio {
+            ThreadPriority::Idle => (PriorityClass::Idle, 0),
+            ThreadPriority::Low => (PriorityClass::Background, 0),
+            ThreadPriority::Normal => (PriorityClass::User, 0),
+            ThreadPriority::High => (PriorityClass::User, -1),
+            ThreadPriority::Realtime => (PriorityClass::RealTime, 0),
+        };
+        self.priority.set(class, adjust);
+        self.maybe_reschedule_thread();
+    }
+
+    /// Get this thread's base priority, ignoring any donated priority.
+    pub fn base_priority(&self) -> ThreadPriority {
+        match self.priority.class() {
+            PriorityClass::RealTime => ThreadPriority::Realtime,
+            PriorityClass::User if self.priority.adjust.load(Ordering::SeqCst) < 0 => {
+                ThreadPriority::High
+            }
+            PriorityClass::User => ThreadPriority::Normal,
+            PriorityClass::Background => ThreadPriority::Low,
+            _ => ThreadPriority::Idle,
+        }
+    }
 }
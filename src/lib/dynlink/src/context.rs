@@ -220,8 +220,15 @@ impl Context {
         for lib in ids {
             self.library_deps.remove_node(lib.0);
         }
-        self.compartment_names.remove(&name);
         self.compartments.remove(comp_id.0);
+        // Two compartments share a name while one is being restarted. If the name was pointing
+        // here, hand it to the one that remains, if any.
+        if self.compartment_names.get(&name) == Some(&comp_id.0) {
+            self.compartment_names.remove(&name);
+            if let Some((idx, _)) = self.compartments.iter().find(|(_, c)| c.name == name) {
+                self.compartment_names.insert(name, idx);
+            }
+        }
     }
 
     /// Create a new compartment with a given name.
@@ -6,10 +6,27 @@ use crate::{Entry, Result};
 // maybe this can be a macro or it's just bad design :(
 pub trait NamerAPI {
     fn put(&self, desc: Descriptor) -> SecGateReturn<Result<()>>;
+    fn put_many(&self, desc: Descriptor) -> SecGateReturn<Result<usize>>;
+    fn link(&self, desc: Descriptor) -> SecGateReturn<Result<()>>;
+    fn symlink(
+        &self,
+        desc: Descriptor,
+        name_len: usize,
+        target_len: usize,
+    ) -> SecGateReturn<Result<()>>;
     fn get(&self, desc: Descriptor) -> SecGateReturn<Result<Entry>>;
+    fn get_no_follow(&self, desc: Descriptor) -> SecGateReturn<Result<Entry>>;
     fn open_handle(&self) -> SecGateReturn<Option<(Descriptor, ObjID)>>;
     fn close_handle(&self, desc: Descriptor) -> SecGateReturn<()>;
     fn enumerate_names(&self, desc: Descriptor) -> SecGateReturn<Result<usize>>;
+    fn enumerate_names_paged(
+        &self,
+        desc: Descriptor,
+        offset: usize,
+        max: usize,
+    ) -> SecGateReturn<Result<(usize, usize)>>;
     fn remove(&self, desc: Descriptor, recursive: bool) -> SecGateReturn<Result<()>>;
     fn change_namespace(&self, desc: Descriptor) -> SecGateReturn<Result<()>>;
+    fn cwd(&self, desc: Descriptor) -> SecGateReturn<Result<usize>>;
+    fn flush_namespace(&self, desc: Descriptor) -> SecGateReturn<Result<()>>;
 }
@@ -9,12 +9,20 @@ use crate::{api::NamerAPI, handle::NamingHandle, Entry, Result};
 pub struct DynamicNamerAPI {
     _handle: &'static CompartmentHandle,
     put: DynamicSecGate<'static, (Descriptor,), Result<()>>,
+    put_many: DynamicSecGate<'static, (Descriptor,), Result<usize>>,
+    link: DynamicSecGate<'static, (Descriptor,), Result<()>>,
+    symlink: DynamicSecGate<'static, (Descriptor, usize, usize), Result<()>>,
     get: DynamicSecGate<'static, (Descriptor,), Result<Entry>>,
+    get_no_follow: DynamicSecGate<'static, (Descriptor,), Result<Entry>>,
     open_handle: DynamicSecGate<'static, (), Option<(Descriptor, ObjID)>>,
     close_handle: DynamicSecGate<'static, (Descriptor,), ()>,
     enumerate_names: DynamicSecGate<'static, (Descriptor,), Result<usize>>,
+    enumerate_names_paged:
+        DynamicSecGate<'static, (Descriptor, usize, usize), Result<(usize, usize)>>,
     remove: DynamicSecGate<'static, (Descriptor, bool), Result<()>>,
     change_namespace: DynamicSecGate<'static, (Descriptor,), Result<()>>,
+    cwd: DynamicSecGate<'static, (Descriptor,), Result<usize>>,
+    flush_namespace: DynamicSecGate<'static, (Descriptor,), Result<()>>,
 }
 
 impl NamerAPI for DynamicNamerAPI {
@@ -22,10 +30,31 @@ impl NamerAPI for DynamicNamerAPI {
         (self.put)(desc)
     }
 
+    fn put_many(&self, desc: Descriptor) -> SecGateReturn<Result<usize>> {
+        (self.put_many)(desc)
+    }
+
+    fn link(&self, desc: Descriptor) -> SecGateReturn<Result<()>> {
+        (self.link)(desc)
+    }
+
+    fn symlink(
+        &self,
+        desc: Descriptor,
+        name_len: usize,
+        target_len: usize,
+    ) -> SecGateReturn<Result<()>> {
+        (self.symlink)(desc, name_len, target_len)
+    }
+
     fn get(&self, desc: Descriptor) -> SecGateReturn<Result<Entry>> {
         (self.get)(desc)
     }
 
+    fn get_no_follow(&self, desc: Descriptor) -> SecGateReturn<Result<Entry>> {
+        (self.get_no_follow)(desc)
+    }
+
     fn open_handle(&self) -> SecGateReturn<Option<(Descriptor, ObjID)>> {
         (self.open_handle)()
     }
@@ -38,6 +67,15 @@ impl NamerAPI for DynamicNamerAPI {
         (self.enumerate_names)(desc)
     }
 
+    fn enumerate_names_paged(
+        &self,
+        desc: Descriptor,
+        offset: usize,
+        max: usize,
+    ) -> SecGateReturn<Result<(usize, usize)>> {
+        (self.enumerate_names_paged)(desc, offset, max)
+    }
+
     fn remove(&self, desc: Descriptor, recursive: bool) -> SecGateReturn<Result<()>> {
         (self.remove)(desc, recursive)
     }
@@ -45,6 +83,14 @@ impl NamerAPI for DynamicNamerAPI {
     fn change_namespace(&self, desc: Descriptor) -> SecGateReturn<Result<()>> {
         (self.change_namespace)(desc)
     }
+
+    fn cwd(&self, desc: Descriptor) -> SecGateReturn<Result<usize>> {
+        (self.cwd)(desc)
+    }
+
+    fn flush_namespace(&self, desc: Descriptor) -> SecGateReturn<Result<()>> {
+        (self.flush_namespace)(desc)
+    }
 }
 
 static DYNAMIC_NAMER_API: OnceLock<DynamicNamerAPI> = OnceLock::new();
@@ -61,11 +107,31 @@ pub fn dynamic_namer_api() -> &'static DynamicNamerAPI {
                     .dynamic_gate::<(Descriptor,), Result<()>>("put")
                     .expect("failed to find put gate call")
             },
+            put_many: unsafe {
+                handle
+                    .dynamic_gate::<(Descriptor,), Result<usize>>("put_many")
+                    .expect("failed to find put_many gate call")
+            },
+            link: unsafe {
+                handle
+                    .dynamic_gate::<(Descriptor,), Result<()>>("link")
+                    .expect("failed to find link gate call")
+            },
+            symlink: unsafe {
+                handle
+                    .dynamic_gate::<(Descriptor, usize, usize), Result<()>>("symlink")
+                    .expect("failed to find symlink gate call")
+            },
             get: unsafe {
                 handle
                     .dynamic_gate::<(Descriptor,), Result<Entry>>("get")
                     .expect("failed to find get gate call")
             },
+            get_no_follow: unsafe {
+                handle
+                    .dynamic_gate::<(Descriptor,), Result<Entry>>("get_no_follow")
+                    .expect("failed to find get_no_follow gate call")
+            },
             open_handle: unsafe {
                 handle
                     .dynamic_gate::<(), Option<(Descriptor, ObjID)>>("open_handle")
@@ -81,6 +147,13 @@ pub fn dynamic_namer_api() -> &'static DynamicNamerAPI {
                     .dynamic_gate::<(Descriptor,), Result<usize>>("enumerate_names")
                     .expect("failed to find enumerate_names gate call")
             },
+            enumerate_names_paged: unsafe {
+                handle
+                    .dynamic_gate::<(Descriptor, usize, usize), Result<(usize, usize)>>(
+                        "enumerate_names_paged",
+                    )
+                    .expect("failed to find enumerate_names_paged gate call")
+            },
             remove: unsafe {
                 handle
                     .dynamic_gate::<(Descriptor, bool), Result<()>>("remove")
@@ -91,6 +164,16 @@ pub fn dynamic_namer_api() -> &'static DynamicNamerAPI {
                     .dynamic_gate::<(Descriptor,), Result<()>>("change_namespace")
                     .expect("failed to find change_namespace gate call")
             },
+            cwd: unsafe {
+                handle
+                    .dynamic_gate::<(Descriptor,), Result<usize>>("cwd")
+                    .expect("failed to find cwd gate call")
+            },
+            flush_namespace: unsafe {
+                handle
+                    .dynamic_gate::<(Descriptor,), Result<()>>("flush_namespace")
+                    .expect("failed to find flush_namespace gate call")
+            },
         }
     })
 }
@@ -5,6 +5,8 @@ pub enum ErrorKind {
     NotFound,
     NotNamespace,
     NotFile,
+    LinkLoop,
+    IncompatibleStore,
 }
 
 impl ErrorKind {
@@ -16,6 +18,8 @@ impl ErrorKind {
             NotFound => "Name was not found",
             NotNamespace => "Name isn't a namespace",
             NotFile => "Name is not a file",
+            LinkLoop => "Too many levels of symbolic links",
+            IncompatibleStore => "Name store was written with an incompatible layout",
         }
     }
 }
@@ -34,6 +38,8 @@ impl Into<std::io::ErrorKind> for ErrorKind {
             ErrorKind::NotFound => std::io::ErrorKind::NotFound,
             ErrorKind::NotNamespace => std::io::ErrorKind::NotADirectory,
             ErrorKind::NotFile => std::io::ErrorKind::InvalidFilename,
+            ErrorKind::LinkLoop => std::io::ErrorKind::FilesystemLoop,
+            ErrorKind::IncompatibleStore => std::io::ErrorKind::InvalidData,
         }
     }
 }
@@ -46,6 +52,8 @@ impl Into<twizzler_rt_abi::fd::OpenError> for ErrorKind {
             ErrorKind::NotFound => twizzler_rt_abi::fd::OpenError::LookupFail,
             ErrorKind::NotNamespace => twizzler_rt_abi::fd::OpenError::InvalidArgument,
             ErrorKind::NotFile => twizzler_rt_abi::fd::OpenError::InvalidArgument,
+            ErrorKind::LinkLoop => twizzler_rt_abi::fd::OpenError::LookupFail,
+            ErrorKind::IncompatibleStore => twizzler_rt_abi::fd::OpenError::Other,
         }
     }
 }
@@ -1,7 +1,12 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
 use secgate::util::{Handle, SimpleBuffer};
 use twizzler_rt_abi::object::MapFlags;
 
-use crate::{api::NamerAPI, Entry, EntryType, ErrorKind, Result};
+use crate::{api::NamerAPI, Entry, EntryType, ErrorKind, PutFailure, Result};
 
 pub struct NamingHandle<'a, API: NamerAPI> {
     desc: u32,
@@ -22,7 +27,7 @@ impl<'a, API: NamerAPI> NamingHandle<'a, API> {
         NamingHandle::open(api).ok()
     }
 
-    pub fn put(&mut self, path: &str, val: u128) -> Result<()> {
+    pub fn put<P: AsRef<Path>>(&mut self, path: P, val: u128) -> Result<()> {
         // I should write directly to the simple buffer
         let s = Entry::try_new(path, EntryType::Object(val))?;
 
@@ -34,7 +39,104 @@ impl<'a, API: NamerAPI> NamingHandle<'a, API> {
         self.api.put(self.desc).unwrap()
     }
 
-    pub fn get(&mut self, path: &str) -> Result<u128> {
+    /// Create a symbolic link at `path` to the path `target`, which is looked up each time the
+    /// link is followed.
+    pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, path: P, target: Q) -> Result<()> {
+        let name = path.as_ref().as_os_str().as_encoded_bytes();
+        let target = target.as_ref().as_os_str().as_encoded_bytes();
+        let _handle = self.buffer.write(&[name, target].concat());
+
+        self.api
+            .symlink(self.desc, name.len(), target.len())
+            .unwrap()
+    }
+
+    /// Put the object currently named by `existing` at `path` as well. Unlike a symbolic link,
+    /// `path` keeps naming that object if `existing` is later moved or replaced. The server looks
+    /// up `existing` and puts `path` under one lock, so `existing` cannot change in between.
+    pub fn link<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, path: P, existing: Q) -> Result<()> {
+        let entries = [
+            Entry::try_new(path, EntryType::Name)?,
+            Entry::try_new(existing, EntryType::Name)?,
+        ];
+        let bytes = unsafe {
+            std::mem::transmute::<[Entry; 2], [u8; 2 * std::mem::size_of::<Entry>()]>(entries)
+        };
+        let _handle = self.buffer.write(&bytes);
+
+        self.api.link(self.desc).unwrap()
+    }
+
+    /// Put a batch of names in one go. The server inserts each batch under a single lock, and a
+    /// failing name does not stop the rest of the batch. On failure, every name that could not be
+    /// inserted is returned along with the reason.
+    pub fn put_many(
+        &mut self,
+        entries: &[(&str, u128)],
+    ) -> std::result::Result<(), Vec<(String, ErrorKind)>> {
+        let mut failures = Vec::new();
+        let mut batch = Vec::new();
+        for (path, val) in entries {
+            match Entry::try_new(path, EntryType::Object(*val)) {
+                Ok(entry) => batch.push((*path, entry)),
+                Err(e) => failures.push((path.to_string(), e)),
+            }
+        }
+
+        // The batch is sent as a u64 count followed by that many entries.
+        let per_call =
+            (self.buffer.max_len() - std::mem::size_of::<u64>()) / std::mem::size_of::<Entry>();
+        for chunk in batch.chunks(per_call) {
+            let mut bytes = Vec::with_capacity(
+                std::mem::size_of::<u64>() + chunk.len() * std::mem::size_of::<Entry>(),
+            );
+            bytes.extend_from_slice(&(chunk.len() as u64).to_ne_bytes());
+            for (_, entry) in chunk {
+                let entry_bytes = unsafe {
+                    std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(*entry)
+                };
+                bytes.extend_from_slice(&entry_bytes);
+            }
+            let _handle = self.buffer.write(&bytes);
+
+            let count = match self.api.put_many(self.desc) {
+                Ok(Ok(count)) => count,
+                Ok(Err(e)) => {
+                    failures.extend(chunk.iter().map(|(path, _)| (path.to_string(), e)));
+                    continue;
+                }
+                Err(_) => {
+                    failures.extend(
+                        chunk
+                            .iter()
+                            .map(|(path, _)| (path.to_string(), ErrorKind::Other)),
+                    );
+                    continue;
+                }
+            };
+
+            let mut buf_vec = vec![0u8; count * std::mem::size_of::<PutFailure>()];
+            self.buffer.read(&mut buf_vec);
+            for i in 0..count {
+                let failure = unsafe {
+                    (buf_vec.as_ptr() as *const PutFailure)
+                        .add(i)
+                        .read_unaligned()
+                };
+                if let Some((path, _)) = chunk.get(failure.index) {
+                    failures.push((path.to_string(), failure.error));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    pub fn get<P: AsRef<Path>>(&mut self, path: P) -> Result<u128> {
         let s = Entry::try_new(path, EntryType::Name)?; // Todo: Find better pattern to describe entries
 
         let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
@@ -46,7 +148,18 @@ impl<'a, API: NamerAPI> NamingHandle<'a, API> {
         }
     }
 
-    pub fn remove(&mut self, path: &str, recursive: bool) -> Result<()> {
+    /// Look up the entry at `path` without following it if it is a symbolic link, so the link
+    /// itself is returned. Links earlier in the path are still followed.
+    pub fn get_no_follow<P: AsRef<Path>>(&mut self, path: P) -> Result<Entry> {
+        let s = Entry::try_new(path, EntryType::Name)?;
+
+        let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
+        let _handle = self.buffer.write(&bytes);
+
+        self.api.get_no_follow(self.desc).unwrap()
+    }
+
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P, recursive: bool) -> Result<()> {
         let s = Entry::try_new(path, EntryType::Namespace)?;
 
         let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
@@ -55,7 +168,7 @@ impl<'a, API: NamerAPI> NamingHandle<'a, API> {
         self.api.remove(self.desc, recursive).unwrap()
     }
 
-    pub fn enumerate_names_relative(&mut self, path: &str) -> Result<Vec<Entry>> {
+    pub fn enumerate_names_relative<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<Entry>> {
         let s = Entry::try_new(path, EntryType::Namespace)?;
 
         let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
@@ -80,11 +193,41 @@ impl<'a, API: NamerAPI> NamingHandle<'a, API> {
         Ok(r_vec)
     }
 
+    /// Enumerate at most `max` names in the namespace at `path`, starting at `offset`. Returns the
+    /// entries along with the total number of names in the namespace, so callers can page through
+    /// namespaces too large to transfer in one call. A call with offset 0 takes a snapshot of the
+    /// namespace, and calls that continue paging through the same path are served from that
+    /// snapshot, so they see a consistent listing and don't hold up other clients.
+    pub fn enumerate_names_paged<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        offset: usize,
+        max: usize,
+    ) -> Result<(Vec<Entry>, usize)> {
+        let s = Entry::try_new(path, EntryType::Namespace)?;
+
+        let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
+        let _handle = self.buffer.write(&bytes);
+
+        let (element_count, total) = self
+            .api
+            .enumerate_names_paged(self.desc, offset, max)
+            .unwrap()?;
+
+        let mut buf_vec = vec![0u8; element_count * std::mem::size_of::<Entry>()];
+        self.buffer.read(&mut buf_vec);
+        let r_vec = (0..element_count)
+            .map(|i| unsafe { (buf_vec.as_ptr() as *const Entry).add(i).read_unaligned() })
+            .collect();
+
+        Ok((r_vec, total))
+    }
+
     pub fn enumerate_names(&mut self) -> Result<Vec<Entry>> {
-        self.enumerate_names_relative(&".")
+        self.enumerate_names_relative(".")
     }
 
-    pub fn change_namespace(&mut self, path: &str) -> Result<()> {
+    pub fn change_namespace<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let s = Entry::try_new(path, EntryType::Namespace)?;
 
         let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
@@ -93,14 +236,30 @@ impl<'a, API: NamerAPI> NamingHandle<'a, API> {
         self.api.change_namespace(self.desc).unwrap()
     }
 
-    pub fn put_namespace(&mut self, path: &str) -> Result<()> {
+    /// Get the namespace that relative paths are resolved against.
+    pub fn cwd(&mut self) -> Result<PathBuf> {
+        let len = self.api.cwd(self.desc).unwrap()?;
+        let mut buf = vec![0u8; len];
+        self.buffer.read(&mut buf);
+        // Safety: the server encoded these bytes from an OsStr on this same platform.
+        let path = unsafe { OsString::from_encoded_bytes_unchecked(buf) };
+        Ok(PathBuf::from(path))
+    }
+
+    /// Write all names back to the naming service's backing store. Names put before this call
+    /// survive a restart of a persistent naming service.
+    pub fn flush(&mut self) -> Result<()> {
+        self.api.flush_namespace(self.desc).unwrap()
+    }
+
+    pub fn put_namespace<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let s = Entry::try_new(path, EntryType::Namespace)?;
         let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
         let _handle = self.buffer.write(&bytes);
         self.api.put(self.desc).unwrap()
     }
 
-    pub fn get_namespace(&mut self, path: &str) -> Result<()> {
+    pub fn get_namespace<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let s = Entry::try_new(path, EntryType::Namespace)?;
 
         let bytes = unsafe { std::mem::transmute::<Entry, [u8; std::mem::size_of::<Entry>()]>(s) };
@@ -9,4 +9,6 @@ mod store;
 pub const MAX_KEY_SIZE: usize = 256;
 
 pub use error::{ErrorKind, Result};
-pub use store::{Entry, EntryType, NameSession, NameStore};
+pub use store::{
+    Entry, EntryName, EntryType, NameSession, NameStore, NamespaceSnapshot, PutFailure,
+};
@@ -1,10 +1,13 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    borrow::Cow,
+    collections::{BTreeMap, HashSet, VecDeque},
+    ffi::{OsStr, OsString},
+    fmt,
+    ops::ControlFlow,
     path::{Component, Path, PathBuf},
-    sync::{Mutex, MutexGuard},
+    sync::{Arc, Mutex, MutexGuard, Weak},
 };
 
-use arrayvec::ArrayString;
 use twizzler::{
     collections::vec::{VecObject, VecObjectAlloc},
     marker::Invariant,
@@ -29,25 +32,133 @@ pub enum EntryType {
     Object(u128),
     #[default]
     Name,
+    // A symbolic link to another path, resolved each time it is looked up. Relative targets are
+    // resolved against the namespace containing the link. Unlike putting the same object under
+    // two names, the link follows whatever is at the target path now. Carrying the target makes
+    // this the largest variant, so it sets the size of every persisted Node.
+    Symlink(EntryName),
+}
+
+// How many symbolic links a single lookup may follow before giving up with LinkLoop
+const MAX_SYMLINK_FOLLOWS: usize = 40;
+
+// The name of a single entry. Names are stored as raw bytes rather than as UTF-8, since names
+// coming from the kernel's init info or from external filesystems need not be valid UTF-8.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EntryName {
+    len: usize,
+    bytes: [u8; MAX_KEY_SIZE],
+}
+
+impl EntryName {
+    pub fn from_bytes(bytes: &[u8]) -> Result<EntryName> {
+        if bytes.len() > MAX_KEY_SIZE {
+            return Err(ErrorKind::InvalidName);
+        }
+        let mut name = EntryName::default();
+        name.bytes[..bytes.len()].copy_from_slice(bytes);
+        name.len = bytes.len();
+        Ok(name)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    // Get the name as UTF-8, if it is valid UTF-8
+    pub fn to_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.as_bytes()).ok()
+    }
+
+    // Get the name as UTF-8, replacing any invalid sequences. Only use this for display, as
+    // distinct names may convert to the same string.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
+    }
+
+    pub fn as_os_str(&self) -> &OsStr {
+        // Safety: OsStr is an arbitrary byte string on every platform naming runs on (Twizzler,
+        // and the Unix hosts used for testing).
+        unsafe { OsStr::from_encoded_bytes_unchecked(self.as_bytes()) }
+    }
+
+    pub fn as_path(&self) -> &Path {
+        Path::new(self.as_os_str())
+    }
+}
+
+impl Default for EntryName {
+    fn default() -> Self {
+        EntryName {
+            len: 0,
+            bytes: [0; MAX_KEY_SIZE],
+        }
+    }
+}
+
+impl PartialEq for EntryName {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for EntryName {}
+
+impl PartialOrd for EntryName {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EntryName {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl AsRef<Path> for EntryName {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl fmt::Display for EntryName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+impl fmt::Debug for EntryName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string_lossy(), f)
+    }
 }
 
 #[repr(C)]
 #[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
 pub struct Entry {
-    pub name: ArrayString<MAX_KEY_SIZE>,
+    pub name: EntryName,
     pub entry_type: EntryType,
 }
 
 impl Entry {
     pub fn try_new<P: AsRef<Path>>(name: P, entry_type: EntryType) -> Result<Entry> {
         Ok(Entry {
-            name: ArrayString::from(name.as_ref().to_str().ok_or(ErrorKind::InvalidName)?)
-                .map_err(|_| ErrorKind::InvalidName)?,
+            name: EntryName::from_bytes(name.as_ref().as_os_str().as_encoded_bytes())?,
             entry_type,
         })
     }
 }
 
+// Reported by the put_many gate for each entry in a batch that could not be inserted
+#[repr(C)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct PutFailure {
+    pub index: usize,
+    pub error: ErrorKind,
+}
+
 #[repr(C)]
 #[derive(Debug, Eq, PartialEq)]
 struct Node {
@@ -71,21 +182,58 @@ impl Node {
             EntryType::Namespace => None,
             EntryType::Object(x) => Some(x),
             EntryType::Name => None,
+            EntryType::Symlink(_) => None,
         }
     }
 
-    fn name(&self) -> ArrayString<MAX_KEY_SIZE> {
-        self.entry.name.clone()
+    fn name(&self) -> EntryName {
+        self.entry.name
     }
 }
 
 unsafe impl Invariant for Node {}
 
+// The root has no parent, so its parent field records the layout of the persisted Nodes instead.
+// Bump the low bits whenever Node, Entry, or EntryType change shape. Parent is the first field of
+// every layout so far, so it can be read before knowing what the rest of the store looks like.
+const STORE_LAYOUT: usize = 0x4e41_4d45_0000_0002;
+
+impl Node {
+    fn root() -> Node {
+        Node {
+            parent: STORE_LAYOUT,
+            curr: 0,
+            entry: Entry::try_new("/", EntryType::Namespace).unwrap(),
+        }
+    }
+}
+
+type Universe = Mutex<VecObject<Node, VecObjectAlloc>>;
+
+// The entries of every open store, by backing object. A store opened on an object that is already
+// open shares the existing entries and their lock, rather than mapping a second copy that would
+// race with the first.
+struct OpenStores(BTreeMap<ObjID, Weak<Universe>>);
+
+unsafe impl Send for OpenStores {}
+
+static OPEN_STORES: Mutex<OpenStores> = Mutex::new(OpenStores(BTreeMap::new()));
+
+impl OpenStores {
+    fn insert(&mut self, id: ObjID, universe: &Arc<Universe>) {
+        self.0.retain(|_, open| open.strong_count() > 0);
+        self.0.insert(id, Arc::downgrade(universe));
+    }
+}
+
 // Ideally when transactions are finished the mutex is unnecessary
 // Though I don't know how to write this without the mutex :think:
 pub struct NameStore {
-    name_universe: Mutex<VecObject<Node, VecObjectAlloc>>,
+    name_universe: Arc<Universe>,
     backing_id: ObjID,
+    // Other stores grafted into this one, by the canonical path they are mounted at.
+    // Mounts are not persisted, so they are lost when the store is reloaded.
+    mounts: Mutex<Vec<(PathBuf, Arc<NameStore>)>>,
 }
 
 unsafe impl Send for NameStore {}
@@ -96,40 +244,49 @@ unsafe impl Sync for NameStore {}
 impl NameStore {
     pub fn new() -> NameStore {
         let mut store = VecObject::new(ObjectBuilder::default().persist()).unwrap();
-        store
-            .push(Node {
-                parent: 0,
-                curr: 0,
-                entry: Entry::try_new("/", EntryType::Namespace).unwrap(),
-            })
-            .unwrap();
+        store.push(Node::root()).unwrap();
         let id = store.object().id();
+        let name_universe = Arc::new(Mutex::new(store));
+        OPEN_STORES.lock().unwrap().insert(id, &name_universe);
         NameStore {
-            name_universe: Mutex::new(store),
+            name_universe,
             backing_id: id,
+            mounts: Mutex::new(Vec::new()),
         }
     }
 
-    // Loads in an existing object store from an Object ID
+    // Loads in an existing object store from an Object ID. If a store on that object is already
+    // open, the two share the same entries (but not mounts).
     pub fn new_in(id: ObjID) -> Result<NameStore> {
+        let mut open = OPEN_STORES.lock().map_err(|_| ErrorKind::Other)?;
+        if let Some(name_universe) = open.0.get(&id).and_then(Weak::upgrade) {
+            return Ok(NameStore {
+                name_universe,
+                backing_id: id,
+                mounts: Mutex::new(Vec::new()),
+            });
+        }
+
         let mut store = VecObject::from(
             Object::map(id, MapFlags::READ | MapFlags::WRITE | MapFlags::PERSIST)
                 .map_err(|_| ErrorKind::NotFound)?,
         );
 
         // todo make "/" not an entry
-        if store.get(0).is_none() {
-            store
-                .push(Node {
-                    parent: 0,
-                    curr: 0,
-                    entry: Entry::try_new("/", EntryType::Namespace).unwrap(),
-                })
-                .unwrap();
+        let layout = store.get(0).map(|root| root.parent);
+        match layout {
+            None => store.push(Node::root()).unwrap(),
+            // Stores written with a different Node layout would be misread, so refuse them rather
+            // than hand out garbage entries. There is no migration between layouts yet.
+            Some(layout) if layout != STORE_LAYOUT => return Err(ErrorKind::IncompatibleStore),
+            Some(_) => {}
         }
+        let name_universe = Arc::new(Mutex::new(store));
+        open.insert(id, &name_universe);
         Ok(NameStore {
-            name_universe: Mutex::new(store),
+            name_universe,
             backing_id: id,
+            mounts: Mutex::new(Vec::new()),
         })
     }
 
@@ -137,6 +294,28 @@ impl NameStore {
         self.backing_id
     }
 
+    // Whether the store's entries outlive this process, i.e. its backing object is persistent
+    pub fn is_persistent(&self) -> bool {
+        self.name_universe
+            .lock()
+            .map(|store| {
+                store
+                    .object()
+                    .handle()
+                    .map_flags()
+                    .contains(MapFlags::PERSIST)
+            })
+            .unwrap_or(false)
+    }
+
+    // Write all entries back to the backing object, so that changes made so far survive a reload
+    // with new_in. Does nothing for a volatile store.
+    pub fn flush(&self) -> Result<()> {
+        let store = self.name_universe.lock().map_err(|_| ErrorKind::Other)?;
+        store.object().sync();
+        Ok(())
+    }
+
     // session is created from root
     pub fn new_session(&self, namespace: &Path) -> NameSession<'_> {
         let mut path = PathBuf::from("/");
@@ -157,34 +336,230 @@ impl NameStore {
 
 // Hopefully this session will do transactions! That will solve all my problems
 // and data races...
+//
+// Every operation that takes a path resolves it the same way: absolute paths start at root,
+// and all other paths are relative to the session's working namespace (see cwd).
 pub struct NameSession<'a> {
     store: &'a NameStore,
     working_ns: PathBuf,
 }
 
+// A copy of a namespace's entries, taken while holding the store lock once. Reading from a
+// snapshot never touches the store, so paging through a large namespace does not block other
+// sessions. Changes made after the snapshot was taken are not reflected in it.
+#[derive(Clone, Debug, Default)]
+pub struct NamespaceSnapshot {
+    entries: Vec<Entry>,
+}
+
+impl NamespaceSnapshot {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Up to max entries starting at offset. Offsets past the end give an empty page.
+    pub fn page(&self, offset: usize, max: usize) -> &[Entry] {
+        let start = offset.min(self.entries.len());
+        let end = start.saturating_add(max).min(self.entries.len());
+        &self.entries[start..end]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Entry> {
+        self.entries.iter()
+    }
+}
+
+impl IntoIterator for NamespaceSnapshot {
+    type Item = Entry;
+    type IntoIter = std::vec::IntoIter<Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+// Where a path leads once mounts are taken into account
+enum Resolved {
+    // At or below the mount point (the first field), at an absolute path (the last field) within
+    // the mounted store
+    Mounted(PathBuf, Arc<NameStore>, PathBuf),
+    // Within this store, at an absolute path
+    Local(PathBuf),
+}
+
 impl NameSession<'_> {
-    // This function will return a reference to an entry described by name: P relative to working_ns
-    // If the name is absolute then it will start at root instead of the working_ns
-    fn namei<'a, P: AsRef<Path>>(
-        &self,
-        store: &'a MutexGuard<'a, VecObject<Node, VecObjectAlloc>>,
-        name: P,
-    ) -> Result<Ref<'a, Node>> {
-        // interpret path based on working directory
-        let path = match name.as_ref().has_root() {
+    // The namespace that relative paths are resolved against
+    pub fn cwd(&self) -> PathBuf {
+        self.working_ns.clone()
+    }
+
+    // Write all entries in the store back to its backing object. See NameStore::flush
+    pub fn flush(&self) -> Result<()> {
+        self.store.flush()
+    }
+
+    // Whether the entry at name is persistent. All entries live in the same store, so this is
+    // the store's persistence, but the entry must exist.
+    pub fn is_persistent<P: AsRef<Path>>(&self, name: P) -> Result<bool> {
+        let name = match self.resolve_mounts(name) {
+            Resolved::Mounted(_, store, rest) => return store.root_session().is_persistent(rest),
+            Resolved::Local(path) => path,
+        };
+        {
+            let store = self
+                .store
+                .name_universe
+                .lock()
+                .map_err(|_| ErrorKind::Other)?;
+            self.namei(&store, name)?;
+        }
+        Ok(self.store.is_persistent())
+    }
+
+    // Interprets name relative to working_ns, unless it is absolute
+    fn resolve<P: AsRef<Path>>(&self, name: P) -> PathBuf {
+        match name.as_ref().has_root() {
             true => PathBuf::from(name.as_ref()),
             false => {
                 let mut path = self.working_ns.clone();
                 path.extend(name.as_ref());
                 path
             }
+        }
+    }
+
+    // Resolves name, and finds the innermost mount, if any, that it leads into. Mount points are
+    // matched against the path with . and .. removed. A path that passes through a mount point
+    // and then climbs back out of it through .. is looked up in this store by that normalized
+    // path, since its components inside the mount only exist in the mounted store. Other paths
+    // are looked up component by component as usual.
+    fn resolve_mounts<P: AsRef<Path>>(&self, name: P) -> Resolved {
+        let path = self.resolve(name);
+        let Ok(mounts) = self.store.mounts.lock() else {
+            return Resolved::Local(path);
+        };
+        if mounts.is_empty() {
+            return Resolved::Local(path);
+        }
+
+        let mut normal = PathBuf::from("/");
+        let mut crossed = false;
+        for item in path.components() {
+            match item {
+                Component::Normal(os_str) => normal.push(os_str),
+                // As with walking up the tree, .. at the root stays at the root.
+                Component::ParentDir => {
+                    normal.pop();
+                }
+                _ => {}
+            }
+            crossed |= mounts.iter().any(|(at, _)| normal.starts_with(at));
+        }
+
+        let mount = mounts
+            .iter()
+            .filter(|(at, _)| normal.starts_with(at))
+            .max_by_key(|(at, _)| at.components().count());
+        match mount {
+            Some((at, store)) => {
+                // Unwrap-Ok: we just checked that normal starts with at.
+                let rest = Path::new("/").join(normal.strip_prefix(at).unwrap());
+                Resolved::Mounted(at.clone(), store.clone(), rest)
+            }
+            None if crossed => Resolved::Local(normal),
+            None => Resolved::Local(path),
+        }
+    }
+
+    // Mount the store backed by object id at the namespace at, so that lookups at or below at
+    // are served from the root of that store. The namespace at, and anything in it, is hidden
+    // until the store is unmounted. If the store is already open, here or elsewhere in this
+    // process, the mount shares its entries rather than opening a second copy.
+    pub fn mount<P: AsRef<Path>>(&self, at: P, id: ObjID) -> Result<()> {
+        let at = match self.resolve_mounts(at) {
+            // Mounting over the root of a mounted store would hide that mount.
+            Resolved::Mounted(_, _, rest) if rest == Path::new("/") => {
+                return Err(ErrorKind::InvalidName)
+            }
+            Resolved::Mounted(_, store, rest) => return store.root_session().mount(rest, id),
+            Resolved::Local(path) => path,
+        };
+        // Mount points are kept by canonical path, so that they can be matched against lookups.
+        let (at, entry_type) = {
+            let store = self
+                .store
+                .name_universe
+                .lock()
+                .map_err(|_| ErrorKind::Other)?;
+            self.construct_canonical(&store, at, true)?
         };
+        if at == Path::new("/") || id == self.store.id() {
+            return Err(ErrorKind::InvalidName);
+        }
+        if entry_type != EntryType::Namespace {
+            return Err(ErrorKind::NotNamespace);
+        }
+        let store = NameStore::new_in(id)?;
+
+        let mut mounts = self.store.mounts.lock().map_err(|_| ErrorKind::Other)?;
+        if mounts.iter().any(|(point, _)| *point == at) {
+            return Err(ErrorKind::InvalidName);
+        }
+        mounts.push((at, Arc::new(store)));
+        Ok(())
+    }
+
+    // Remove the mount at the namespace at, uncovering whatever it hid.
+    pub fn unmount<P: AsRef<Path>>(&self, at: P) -> Result<()> {
+        match self.resolve_mounts(at) {
+            Resolved::Mounted(at, _, rest) if rest == Path::new("/") => {
+                let mut mounts = self.store.mounts.lock().map_err(|_| ErrorKind::Other)?;
+                mounts.retain(|(point, _)| *point != at);
+                Ok(())
+            }
+            Resolved::Mounted(_, store, rest) => store.root_session().unmount(rest),
+            Resolved::Local(_) => Err(ErrorKind::NotFound),
+        }
+    }
+
+    // This function will return a reference to an entry described by name: P relative to working_ns
+    // If the name is absolute then it will start at root instead of the working_ns
+    // Symbolic links are followed, including one at the end of the path
+    fn namei<'a, P: AsRef<Path>>(
+        &self,
+        store: &'a MutexGuard<'a, VecObject<Node, VecObjectAlloc>>,
+        name: P,
+    ) -> Result<Ref<'a, Node>> {
+        self.namei_follow(store, name, true)
+    }
+
+    // Like namei, but a symbolic link at the end of the path is only followed if follow_last is
+    // set, so that operations on the link itself can find it. Links in the middle of the path are
+    // always followed.
+    fn namei_follow<'a, P: AsRef<Path>>(
+        &self,
+        store: &'a MutexGuard<'a, VecObject<Node, VecObjectAlloc>>,
+        name: P,
+        follow_last: bool,
+    ) -> Result<Ref<'a, Node>> {
+        let path = self.resolve(name);
 
         let mut index = 0;
+        let mut follows = 0;
+        // Components still to be traversed. Following a link splices its target in at the front.
+        let mut remaining: VecDeque<PathBuf> = path
+            .components()
+            .map(|item| PathBuf::from(item.as_os_str()))
+            .collect();
         // traverse store based on path's components
-        for item in path.components() {
+        while let Some(component) = remaining.pop_front() {
             let mut found = false;
-            match item {
+            // Unwrap-Ok: every element was made from a single component.
+            match component.components().next().unwrap() {
                 Component::Prefix(_) => {
                     continue;
                 }
@@ -194,18 +569,34 @@ impl NameSession<'_> {
                 }
                 Component::CurDir => continue,
                 Component::ParentDir => {
-                    index = store.get(index).unwrap().parent;
+                    // The parent of the root is the root
+                    if index != 0 {
+                        index = store.get(index).unwrap().parent;
+                    }
                     continue;
                 }
                 Component::Normal(os_str) => {
                     for i in 0..store.len() {
                         let node = store.get(i).unwrap();
-                        if node.entry.name.as_str()
-                            == os_str.to_str().ok_or(ErrorKind::InvalidName)?
+                        if node.entry.name.as_bytes() == os_str.as_encoded_bytes()
                             && node.parent == index
                         {
-                            index = i;
                             found = true;
+                            if let EntryType::Symlink(target) = node.entry.entry_type {
+                                if follow_last || !remaining.is_empty() {
+                                    follows += 1;
+                                    if follows > MAX_SYMLINK_FOLLOWS {
+                                        return Err(ErrorKind::LinkLoop);
+                                    }
+                                    // Relative targets start from the link's namespace, which
+                                    // is where index still points.
+                                    for item in target.as_path().components().rev() {
+                                        remaining.push_front(PathBuf::from(item.as_os_str()));
+                                    }
+                                    break;
+                                }
+                            }
+                            index = i;
                             break;
                         }
                     }
@@ -221,35 +612,91 @@ impl NameSession<'_> {
     }
 
     // Traverses the path and construct the canonical path given name relative to absolute path
+    // A symbolic link at the end of the path is followed if follow_last is set, see namei_follow
     fn construct_canonical<'a, P: AsRef<Path>>(
         &self,
         store: &'a MutexGuard<'a, VecObject<Node, VecObjectAlloc>>,
         name: P,
+        follow_last: bool,
     ) -> Result<(PathBuf, EntryType)> {
-        let mut vec = VecDeque::<String>::new();
+        let mut vec = VecDeque::<OsString>::new();
 
-        let mut node = self.namei(&store, &name)?;
+        let mut node = self.namei_follow(&store, &name, follow_last)?;
 
         let mut current = node.curr;
         while current != 0 {
             node = store.get(current).unwrap();
-            vec.push_front(node.entry.name.to_string());
+            vec.push_front(node.entry.name.as_os_str().to_owned());
             current = node.parent;
         }
 
-        vec.push_front("/".to_owned());
+        vec.push_front("/".into());
 
         Ok((PathBuf::from_iter(vec), node.entry.entry_type))
     }
 
     pub fn put<P: AsRef<Path>>(&self, name: P, val: EntryType) -> Result<()> {
+        let name = match self.resolve_mounts(name) {
+            Resolved::Mounted(_, store, rest) => return store.root_session().put(rest, val),
+            Resolved::Local(path) => path,
+        };
         let mut store = self
             .store
             .name_universe
             .lock()
             .map_err(|_| ErrorKind::Other)?;
+        self.put_locked(&mut store, name, val)
+    }
+
+    // Inserts all entries while holding the store lock once. Entries are inserted in order, so
+    // later entries may refer to namespaces created by earlier ones. A failing entry does not
+    // stop the rest of the batch; every failure is reported with the index of the entry that
+    // caused it. As with put, an entry whose name already exists overwrites it, so when a batch
+    // names the same entry twice the later one wins and neither is reported as a failure.
+    pub fn put_many<P: AsRef<Path>>(
+        &self,
+        entries: &[(P, EntryType)],
+    ) -> std::result::Result<(), Vec<PutFailure>> {
+        let mut store = match self.store.name_universe.lock() {
+            Ok(store) => store,
+            Err(_) => {
+                return Err((0..entries.len())
+                    .map(|index| PutFailure {
+                        index,
+                        error: ErrorKind::Other,
+                    })
+                    .collect())
+            }
+        };
+        let failures: Vec<_> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (name, val))| {
+                let res = match self.resolve_mounts(name) {
+                    Resolved::Mounted(_, mounted, rest) => mounted.root_session().put(rest, *val),
+                    Resolved::Local(path) => self.put_locked(&mut store, path, *val),
+                };
+                res.err().map(|error| PutFailure { index, error })
+            })
+            .collect();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    fn put_locked<P: AsRef<Path>>(
+        &self,
+        store: &mut MutexGuard<'_, VecObject<Node, VecObjectAlloc>>,
+        name: P,
+        val: EntryType,
+    ) -> Result<()> {
+        // Resolve up front so the parent lookup below sees the same path as the first lookup
+        let name = self.resolve(name);
         let entry = {
-            let current_entry = self.namei(&store, &name);
+            // Putting over a symbolic link replaces the link, rather than what it points to.
+            let current_entry = self.namei_follow(&store, &name, false);
             let _ = match current_entry {
                 Ok(node) => {
                     unsafe {
@@ -289,6 +736,10 @@ impl NameSession<'_> {
     }
 
     pub fn get<P: AsRef<Path>>(&self, name: P) -> Result<Entry> {
+        let name = match self.resolve_mounts(name) {
+            Resolved::Mounted(_, store, rest) => return store.root_session().get(rest),
+            Resolved::Local(path) => path,
+        };
         let store = self
             .store
             .name_universe
@@ -300,14 +751,91 @@ impl NameSession<'_> {
         Ok(entry)
     }
 
-    pub fn enumerate_namespace<P: AsRef<Path>>(&self, name: P) -> Result<std::vec::Vec<Entry>> {
+    // Like get, but if name is itself a symbolic link, returns the link rather than what it points
+    // to. Links earlier in the path are still followed.
+    pub fn get_no_follow<P: AsRef<Path>>(&self, name: P) -> Result<Entry> {
+        let name = match self.resolve_mounts(name) {
+            Resolved::Mounted(_, store, rest) => return store.root_session().get_no_follow(rest),
+            Resolved::Local(path) => path,
+        };
         let store = self
             .store
             .name_universe
             .lock()
             .map_err(|_| ErrorKind::Other)?;
+        let node = self.namei_follow(&store, name, false)?;
+
+        let entry = (*node).entry;
+        Ok(entry)
+    }
+
+    // Create a symbolic link at name to the path target. The target need not exist yet, and is
+    // only looked up when the link is followed. Links are resolved within the store that holds
+    // them, so a link does not reach into stores mounted elsewhere.
+    pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, name: P, target: Q) -> Result<()> {
+        let target = EntryName::from_bytes(target.as_ref().as_os_str().as_encoded_bytes())?;
+        self.put(name, EntryType::Symlink(target))
+    }
 
+    // Put the object that existing currently names at name as well. The two names are
+    // independent afterwards: moving or replacing existing does not affect name. When both names
+    // are in the same store, the lookup and insert happen under one lock, so existing cannot
+    // change in between. Names in different stores are linked with a separate get and put.
+    pub fn link<P: AsRef<Path>, Q: AsRef<Path>>(&self, name: P, existing: Q) -> Result<()> {
+        let (name, existing) = (name.as_ref(), existing.as_ref());
+        match (self.resolve_mounts(name), self.resolve_mounts(existing)) {
+            (Resolved::Local(name), Resolved::Local(existing)) => {
+                let mut store = self
+                    .store
+                    .name_universe
+                    .lock()
+                    .map_err(|_| ErrorKind::Other)?;
+                let id = match self.namei(&store, existing)?.entry.entry_type {
+                    EntryType::Object(id) => id,
+                    _ => return Err(ErrorKind::NotFile),
+                };
+                self.put_locked(&mut store, name, EntryType::Object(id))
+            }
+            (Resolved::Mounted(_, store, name), Resolved::Mounted(_, other, existing))
+                if Arc::ptr_eq(&store, &other) =>
+            {
+                store.root_session().link(name, existing)
+            }
+            _ => match self.get(existing)?.entry_type {
+                EntryType::Object(id) => self.put(name, EntryType::Object(id)),
+                _ => Err(ErrorKind::NotFile),
+            },
+        }
+    }
+
+    pub fn enumerate_namespace<P: AsRef<Path>>(&self, name: P) -> Result<std::vec::Vec<Entry>> {
         let mut vec = std::vec::Vec::new();
+        self.for_each_entry(name, |entry| {
+            vec.push(*entry);
+            ControlFlow::Continue(())
+        })?;
+        Ok(vec)
+    }
+
+    // Calls f with each entry in the namespace at name, until f returns ControlFlow::Break. Nothing
+    // is collected, so callers that only want to find or count entries don't allocate. The store
+    // is locked while f runs, so f must be brief and must not use the store.
+    pub fn for_each_entry<P: AsRef<Path>, F: FnMut(&Entry) -> ControlFlow<()>>(
+        &self,
+        name: P,
+        mut f: F,
+    ) -> Result<()> {
+        let name = match self.resolve_mounts(name) {
+            Resolved::Mounted(_, store, rest) => {
+                return store.root_session().for_each_entry(rest, f)
+            }
+            Resolved::Local(path) => path,
+        };
+        let store = self
+            .store
+            .name_universe
+            .lock()
+            .map_err(|_| ErrorKind::Other)?;
 
         let node = self.namei(&store, name)?;
 
@@ -317,21 +845,60 @@ impl NameSession<'_> {
 
         for i in 1..store.len() {
             let search = store.get(i).unwrap();
-            if search.parent == node.curr {
-                vec.push((*search).entry);
+            if search.parent == node.curr && f(&search.entry).is_break() {
+                break;
             }
         }
 
-        Ok(vec)
+        Ok(())
+    }
+
+    // Like enumerate_namespace, but returns the entries as a snapshot that can be paged through
+    // without going back to the store.
+    pub fn enumerate_snapshot<P: AsRef<Path>>(&self, name: P) -> Result<NamespaceSnapshot> {
+        Ok(NamespaceSnapshot {
+            entries: self.enumerate_namespace(name)?,
+        })
+    }
+
+    // Like enumerate_namespace, but only collects up to max entries starting at offset. Also
+    // returns the total number of entries in the namespace so callers can page through it.
+    pub fn enumerate_namespace_range<P: AsRef<Path>>(
+        &self,
+        name: P,
+        offset: usize,
+        max: usize,
+    ) -> Result<(std::vec::Vec<Entry>, usize)> {
+        let mut vec = std::vec::Vec::new();
+        let mut total = 0;
+        self.for_each_entry(name, |entry| {
+            if total >= offset && vec.len() < max {
+                vec.push(*entry);
+            }
+            total += 1;
+            ControlFlow::Continue(())
+        })?;
+
+        Ok((vec, total))
     }
 
     pub fn change_namespace<P: AsRef<Path>>(&mut self, name: P) -> Result<()> {
+        let name = match self.resolve_mounts(name) {
+            Resolved::Mounted(at, store, rest) => {
+                let mut session = store.root_session();
+                session.change_namespace(rest)?;
+                // Unwrap-Ok: the session's working namespace is always absolute.
+                self.working_ns = at.join(session.working_ns.strip_prefix("/").unwrap());
+                return Ok(());
+            }
+            Resolved::Local(path) => path,
+        };
         let store = self
             .store
             .name_universe
             .lock()
             .map_err(|_| ErrorKind::Other)?;
-        let (canonical_name, entry) = self.construct_canonical(&store, name)?;
+        let (canonical_name, entry) = self.construct_canonical(&store, name, true)?;
         match entry {
             EntryType::Namespace => {
                 self.working_ns = PathBuf::from(canonical_name);
@@ -342,13 +909,21 @@ impl NameSession<'_> {
     }
 
     pub fn remove<P: AsRef<Path>>(&self, name: P, recursive: bool) -> Result<()> {
+        let name = match self.resolve_mounts(name) {
+            Resolved::Mounted(_, store, rest) => {
+                return store.root_session().remove(rest, recursive)
+            }
+            Resolved::Local(path) => path,
+        };
+
         let mut store = self
             .store
             .name_universe
             .lock()
             .map_err(|_| ErrorKind::Other)?;
 
-        let entry = self.namei(&store, &name)?;
+        // Removing a symbolic link removes the link, rather than what it points to.
+        let entry = self.namei_follow(&store, &name, false)?;
         let index = entry.curr;
         if !recursive && entry.entry.entry_type == EntryType::Namespace {
             return Err(ErrorKind::NotFile);
@@ -356,9 +931,16 @@ impl NameSession<'_> {
         if entry.curr == 0 {
             return Err(ErrorKind::InvalidName);
         }
-
         drop(entry);
 
+        // Removing a namespace that contains a mount point would leave the mount dangling.
+        let (canonical, _) = self.construct_canonical(&store, &name, false)?;
+        let mounts = self.store.mounts.lock().map_err(|_| ErrorKind::Other)?;
+        if mounts.iter().any(|(at, _)| at.starts_with(&canonical)) {
+            return Err(ErrorKind::InvalidName);
+        }
+        drop(mounts);
+
         // Copies a node to another index. If it's a directory
         // it will fix all the child nodes if they exist
         unsafe fn swap_node(
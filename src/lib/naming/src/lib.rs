@@ -2,7 +2,7 @@
 extern "C" {}
 
 use naming_core::{api::NamerAPI, handle::NamingHandle, Result};
-pub use naming_core::{dynamic::*, Entry, EntryType};
+pub use naming_core::{dynamic::*, Entry, EntryName, EntryType};
 use secgate::util::Descriptor;
 use twizzler_rt_abi::object::ObjID;
 
@@ -13,10 +13,31 @@ impl NamerAPI for StaticNamingAPI {
         naming_srv::put(desc)
     }
 
+    fn put_many(&self, desc: Descriptor) -> secgate::SecGateReturn<Result<usize>> {
+        naming_srv::put_many(desc)
+    }
+
+    fn link(&self, desc: Descriptor) -> secgate::SecGateReturn<Result<()>> {
+        naming_srv::link(desc)
+    }
+
+    fn symlink(
+        &self,
+        desc: Descriptor,
+        name_len: usize,
+        target_len: usize,
+    ) -> secgate::SecGateReturn<Result<()>> {
+        naming_srv::symlink(desc, name_len, target_len)
+    }
+
     fn get(&self, desc: Descriptor) -> secgate::SecGateReturn<Result<Entry>> {
         naming_srv::get(desc)
     }
 
+    fn get_no_follow(&self, desc: Descriptor) -> secgate::SecGateReturn<Result<Entry>> {
+        naming_srv::get_no_follow(desc)
+    }
+
     fn open_handle(&self) -> secgate::SecGateReturn<Option<(Descriptor, ObjID)>> {
         naming_srv::open_handle()
     }
@@ -29,6 +50,15 @@ impl NamerAPI for StaticNamingAPI {
         naming_srv::enumerate_names(desc)
     }
 
+    fn enumerate_names_paged(
+        &self,
+        desc: Descriptor,
+        offset: usize,
+        max: usize,
+    ) -> secgate::SecGateReturn<Result<(usize, usize)>> {
+        naming_srv::enumerate_names_paged(desc, offset, max)
+    }
+
     fn remove(&self, desc: Descriptor, recursive: bool) -> secgate::SecGateReturn<Result<()>> {
         naming_srv::remove(desc, recursive)
     }
@@ -36,6 +66,14 @@ impl NamerAPI for StaticNamingAPI {
     fn change_namespace(&self, desc: Descriptor) -> secgate::SecGateReturn<Result<()>> {
         naming_srv::change_namespace(desc)
     }
+
+    fn cwd(&self, desc: Descriptor) -> secgate::SecGateReturn<Result<usize>> {
+        naming_srv::cwd(desc)
+    }
+
+    fn flush_namespace(&self, desc: Descriptor) -> secgate::SecGateReturn<Result<()>> {
+        naming_srv::flush_namespace(desc)
+    }
 }
 
 static STATIC_NAMING_API: StaticNamingAPI = StaticNamingAPI {};
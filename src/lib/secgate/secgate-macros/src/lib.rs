@@ -15,6 +15,23 @@ use syn::{
     Type, TypeBareFn, TypePath, Visibility,
 };
 
+/// Turn a function into a secure gate. Options are given as `options(...)`:
+///
+/// - `info`: the first argument is a `&GateCallInfo` describing the caller.
+/// - `api`: only generate the caller side, for crates that call into a gate implemented elsewhere.
+/// - `buffer_return`: return the value through a buffer object owned by the callee, instead of on
+///   the stack. The caller maps the buffer read-only to decode the value.
+///
+/// A `#[deprecated]` attribute on the function is applied to the generated public function only, so
+/// callers get the usual deprecation warning while the generated glue does not:
+///
+/// ```ignore
+/// #[secure_gate]
+/// #[deprecated = "use new_gate instead"]
+/// pub fn old_gate() -> u32 { 0 }
+///
+/// old_gate(); // warning: use of deprecated function `old_gate`: use new_gate instead
+/// ```
 #[proc_macro_attribute]
 pub fn secure_gate(
     attr: proc_macro::TokenStream,
@@ -42,6 +59,8 @@ struct Info {
     pub ret_type: ReturnType,
     pub arg_names: Vec<Ident>,
     pub has_info: bool,
+    pub buffer_return: bool,
+    pub deprecated: Vec<Attribute>,
 }
 
 #[derive(Debug, FromMeta)]
@@ -56,6 +75,8 @@ fn build_names(
     ret_type: ReturnType,
     arg_names: Vec<Ident>,
     has_info: bool,
+    buffer_return: bool,
+    deprecated: Vec<Attribute>,
 ) -> Info {
     Info {
         mod_name: Ident::new(&format!("{}{}_mod", PREFIX, base), base.span()),
@@ -70,6 +91,8 @@ fn build_names(
         arg_names,
         ret_type,
         has_info,
+        buffer_return,
+        deprecated,
     }
 }
 
@@ -79,6 +102,14 @@ fn handle_secure_gate(
 ) -> Result<proc_macro2::TokenStream, Error> {
     let mut tree = syn::parse2::<syn::ItemFn>(item)?;
 
+    // Deprecation only applies to the public call point. Everything else is generated glue that
+    // calls the implementation, and would otherwise warn inside the gate's own crate.
+    let (deprecated, attrs) = tree
+        .attrs
+        .into_iter()
+        .partition(|attr| attr.path().is_ident("deprecated"));
+    tree.attrs = attrs;
+
     let types: Vec<_> = tree
         .sig
         .inputs
@@ -104,8 +135,13 @@ fn handle_secure_gate(
 
     let opt_info: Ident = parse_quote!(info);
     let opt_api: Ident = parse_quote!(api);
+    let opt_buffer_return: Ident = parse_quote!(buffer_return);
 
     let entry_only = attr_args.options.iter().any(|item| item.is_ident(&opt_api));
+    let buffer_return = attr_args
+        .options
+        .iter()
+        .any(|item| item.is_ident(&opt_buffer_return));
 
     let has_info = if attr_args
         .options
@@ -157,7 +193,15 @@ fn handle_secure_gate(
     let ret_type = tree.sig.output.clone();
 
     let fn_name = tree.sig.ident.clone();
-    let names = build_names(fn_name, types, ret_type, arg_names, has_info);
+    let names = build_names(
+        fn_name,
+        types,
+        ret_type,
+        arg_names,
+        has_info,
+        buffer_return,
+        deprecated,
+    );
     let trampoline = build_trampoline(&tree, &names)?;
     let extern_trampoline = build_extern_trampoline(&tree, &names)?;
     let public_call_point = build_public_call(&tree, &names)?;
@@ -287,6 +331,7 @@ fn build_entry(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenStream,
         internal_fn_name,
         arg_names: all_arg_names,
         has_info,
+        buffer_return,
         ..
     } = names;
     call_point.sig.ident = entry_name.clone();
@@ -309,6 +354,25 @@ fn build_entry(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenStream,
         quote! {#(#arg_names),*}
     };
 
+    let wrap_ret = if *buffer_return {
+        quote! {
+            let wret = match impl_ret {
+                Ok(r) => match secgate::util::ReturnBuffer::write(unsafe {&(*info).canonicalize()}, &r) {
+                    Some(rb) => secgate::SecGateReturn::Success(rb),
+                    None => secgate::SecGateReturn::<_>::NoReturnValue,
+                },
+                Err(_) => secgate::SecGateReturn::<_>::CalleePanic,
+            };
+        }
+    } else {
+        quote! {
+            let wret = match impl_ret {
+                Ok(r) => secgate::SecGateReturn::Success(r),
+                Err(_) => secgate::SecGateReturn::<_>::CalleePanic,
+            };
+        }
+    };
+
     call_point.block = Box::new(parse2(quote::quote! {
         {
             if unsafe {(*info)}.source_context().is_some() {
@@ -322,10 +386,7 @@ fn build_entry(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenStream,
             if impl_ret.is_err() {
                 std::process::Termination::report(std::process::ExitCode::from(101u8));
             }
-            let wret = match impl_ret {
-                Ok(r) => secgate::SecGateReturn::Success(r),
-                Err(_) => secgate::SecGateReturn::<_>::CalleePanic,
-            };
+            #wrap_ret
 
             // Success -- write the return value.
             let ret = unsafe {ret.as_mut().unwrap()};
@@ -339,6 +400,7 @@ fn build_entry(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenStream,
 fn build_public_call(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenStream, Error> {
     let mut call_point = tree.clone();
     call_point.attrs.push(parse_quote!(#[inline(always)]));
+    call_point.attrs.extend(names.deprecated.iter().cloned());
     call_point.vis = Visibility::Public(Pub::default());
 
     let ret_type = names.ret_type.clone();
@@ -361,6 +423,7 @@ fn build_public_call(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenSt
         trampoline_name_without_prefix,
         arg_names,
         has_info,
+        buffer_return,
         ..
     } = names;
 
@@ -380,6 +443,13 @@ fn build_public_call(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenSt
         }
     };
 
+    // For buffer-return gates, the gate returns where the callee put the value.
+    let unwrap_ret = if *buffer_return {
+        quote! {secgate::util::ReturnBuffer::read(ret)}
+    } else {
+        quote! {ret}
+    };
+
     call_point.block = Box::new(parse2(quote::quote! {
         {
             #args_tuple
@@ -397,7 +467,7 @@ fn build_public_call(tree: &ItemFn, names: &Info) -> Result<proc_macro2::TokenSt
                 })
             });
             secgate::restore_frame(frame);
-            ret
+            #unwrap_ret
         }
     })?);
 
@@ -434,6 +504,7 @@ fn build_types(tree: &ItemFn, names: &Info) -> Result<TokenStream, Error> {
         types,
         ret_type,
         has_info,
+        buffer_return,
         ..
     } = names;
     let entry_sig = get_entry_sig(tree);
@@ -461,6 +532,7 @@ fn build_types(tree: &ItemFn, names: &Info) -> Result<TokenStream, Error> {
     };
 
     let ret_type = match ret_type {
+        _ if *buffer_return => Box::new(parse_quote!(secgate::util::ReturnBuffer)),
         ReturnType::Default => Box::new(parse_quote!(())),
         ReturnType::Type(_, ty) => ty.clone(),
     };
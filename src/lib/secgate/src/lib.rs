@@ -20,6 +20,10 @@ use twizzler_abi::object::ObjID;
 
 pub mod util;
 
+// Lets the gate macro's generated paths resolve in this crate's own tests.
+#[cfg(test)]
+extern crate self as secgate;
+
 /// Enum of possible return codes, similar to [Result], but with specific
 /// variants of possible failures of initializing or invoking the secure gate call.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
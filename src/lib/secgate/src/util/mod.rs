@@ -2,6 +2,8 @@
 
 mod buffer;
 mod handle;
+mod return_buffer;
 
 pub use buffer::*;
 pub use handle::*;
+pub use return_buffer::*;
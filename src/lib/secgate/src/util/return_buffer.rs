@@ -0,0 +1,193 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use twizzler_abi::{
+    object::NULLPAGE_SIZE,
+    syscall::{
+        sys_object_create, sys_object_ctrl, BackingType, DeleteFlags, LifetimeType,
+        ObjectControlCmd, ObjectCreate, ObjectCreateFlags,
+    },
+    thread::{ExecutionState, ThreadRepr},
+};
+use twizzler_rt_abi::object::{MapFlags, ObjID};
+
+use super::SimpleBuffer;
+use crate::{GateCallInfo, SecGateReturn};
+
+/// A type that can be returned from a secure gate through a [ReturnBuffer], rather than on the
+/// stack. Used by gates declared with `options(buffer_return)`.
+pub trait BufferReturn: Sized {
+    /// Encode the value as bytes.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Decode a value from bytes produced by [BufferReturn::to_bytes]. The bytes come from another
+    /// compartment, so implementations must not trust them, and should return None if they are
+    /// invalid.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+impl BufferReturn for Vec<u8> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl BufferReturn for String {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+// Return buffers owned by this compartment, keyed by the calling compartment and thread. Each
+// caller thread gets its own buffer, so concurrent calls do not overwrite each other's values. A
+// buffer is reused for every later buffer-return call from the same caller thread, and freed once
+// a new caller thread arrives and finds that the owning thread has exited.
+static RETURN_BUFFERS: Mutex<BTreeMap<(ObjID, ObjID), SimpleBuffer>> = Mutex::new(BTreeMap::new());
+
+fn new_return_buffer() -> Option<SimpleBuffer> {
+    let id = sys_object_create(
+        ObjectCreate::new(
+            BackingType::Normal,
+            LifetimeType::Volatile,
+            None,
+            ObjectCreateFlags::empty(),
+        ),
+        &[],
+        &[],
+    )
+    .ok()?;
+    let handle =
+        twizzler_rt_abi::object::twz_rt_map_object(id, MapFlags::READ | MapFlags::WRITE).ok()?;
+    Some(SimpleBuffer::new(handle))
+}
+
+// A thread whose repr object can no longer be mapped has been cleaned up, so it counts as exited.
+fn thread_exited(id: ObjID) -> bool {
+    let Ok(handle) = twizzler_rt_abi::object::twz_rt_map_object(id, MapFlags::READ) else {
+        return true;
+    };
+    // Safety: thread repr objects contain the thread repr struct at the base, and the handle keeps
+    // the object mapped for as long as the reference is used.
+    let repr = unsafe { &*(handle.start().add(NULLPAGE_SIZE) as *const ThreadRepr) };
+    repr.get_state() == ExecutionState::Exited
+}
+
+// Free the buffers of caller threads that have exited, since nothing will read them again.
+fn evict_exited(buffers: &mut BTreeMap<(ObjID, ObjID), SimpleBuffer>) {
+    buffers.retain(|(_, thread), sb| {
+        if !thread_exited(*thread) {
+            return true;
+        }
+        let _ = sys_object_ctrl(
+            sb.handle().id(),
+            ObjectControlCmd::Delete(DeleteFlags::empty()),
+        );
+        false
+    });
+}
+
+/// The location of a value returned from a `buffer_return` gate. The callee writes the encoded
+/// value into a buffer object that it created and owns, and returns this in place of the value.
+/// The caller maps the buffer read-only and decodes the value from it.
+///
+/// The callee never maps an object named by the caller, so a caller cannot direct the write at
+/// some other object that the callee can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct ReturnBuffer {
+    id: ObjID,
+    len: usize,
+}
+
+impl ReturnBuffer {
+    /// Callee side: encode `val` into this compartment's return buffer for the caller described
+    /// by `info`, creating the buffer on first use. Returns None if the buffer could not be
+    /// created or is too small for the value.
+    pub fn write<T: BufferReturn>(info: &GateCallInfo, val: &T) -> Option<Self> {
+        let key = (info.source_context().unwrap_or(0.into()), info.thread_id());
+        let mut buffers = RETURN_BUFFERS.lock().ok()?;
+        if !buffers.contains_key(&key) {
+            evict_exited(&mut buffers);
+            buffers.insert(key, new_return_buffer()?);
+        }
+        let sb = buffers.get_mut(&key)?;
+        let bytes = val.to_bytes();
+        if bytes.len() > sb.max_len() {
+            return None;
+        }
+        let len = sb.write(&bytes);
+        Some(Self {
+            id: sb.handle().id(),
+            len,
+        })
+    }
+
+    /// Caller side: decode the value that the callee wrote into its buffer. A successful return
+    /// whose value cannot be read or decoded becomes [SecGateReturn::NoReturnValue].
+    pub fn read<T: BufferReturn>(ret: SecGateReturn<Self>) -> SecGateReturn<T> {
+        let rb = match ret {
+            SecGateReturn::Success(rb) => rb,
+            SecGateReturn::PermissionDenied => return SecGateReturn::PermissionDenied,
+            SecGateReturn::CalleePanic => return SecGateReturn::CalleePanic,
+            SecGateReturn::NoReturnValue => return SecGateReturn::NoReturnValue,
+        };
+        match rb.decode() {
+            Some(val) => SecGateReturn::Success(val),
+            None => SecGateReturn::NoReturnValue,
+        }
+    }
+
+    fn decode<T: BufferReturn>(&self) -> Option<T> {
+        let handle = twizzler_rt_abi::object::twz_rt_map_object(self.id, MapFlags::READ).ok()?;
+        let sb = SimpleBuffer::new(handle);
+        if self.len > sb.max_len() {
+            return None;
+        }
+        let mut bytes = vec![0u8; self.len];
+        sb.read(&mut bytes);
+        T::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    crate::secgate_prelude!();
+
+    #[crate::secure_gate(options(buffer_return))]
+    fn return_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = b"return buffer test!".to_vec();
+        let rb = GateCallInfo::with_alloca(0.into(), 0.into(), |info| {
+            ReturnBuffer::write(info, &data).unwrap()
+        });
+        assert_eq!(rb.len, data.len());
+        assert_eq!(
+            ReturnBuffer::read::<Vec<u8>>(SecGateReturn::Success(rb)),
+            SecGateReturn::Success(data)
+        );
+        assert_eq!(
+            ReturnBuffer::read::<Vec<u8>>(SecGateReturn::CalleePanic),
+            SecGateReturn::CalleePanic
+        );
+    }
+
+    #[test]
+    fn gate_returns_vec() {
+        let ret = return_bytes(1000).unwrap();
+        assert_eq!(ret.len(), 1000);
+        assert!(ret.iter().enumerate().all(|(i, b)| *b == i as u8));
+    }
+}
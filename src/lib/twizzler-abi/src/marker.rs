@@ -38,12 +38,40 @@ unsafe impl ObjSafe for AtomicI64 {}
 unsafe impl ObjSafe for AtomicI8 {}
 unsafe impl ObjSafe for AtomicIsize {}
 
-/// Version for a base type.
-#[derive(Clone, Copy, Debug)]
-pub struct BaseVersion {}
-/// Tag for a base type. Each base type must have a unique tag.
-#[derive(Clone, Copy, Debug)]
-pub struct BaseTag {}
+/// Version for a base type. A freshly created object's metadata holds version 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct BaseVersion(u32);
+
+impl BaseVersion {
+    /// Construct a new base version.
+    pub const fn new(version: u32) -> Self {
+        Self(version)
+    }
+
+    /// Get the raw version number.
+    pub const fn raw(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Tag for a base type. Each base type must have a unique tag. A freshly created object's
+/// metadata holds tag 0, which no base type should use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct BaseTag(u128);
+
+impl BaseTag {
+    /// Construct a new base tag.
+    pub const fn new(tag: u128) -> Self {
+        Self(tag)
+    }
+
+    /// Get the raw tag value.
+    pub const fn raw(&self) -> u128 {
+        self.0
+    }
+}
 /// Trait that all base types must implement.
 #[rustc_on_unimplemented(
     message = "`{Self}` is not safe to be a base type for an object",
@@ -1,5 +1,7 @@
 //! Types that make up object metadata.
 
+use core::sync::atomic::AtomicU64;
+
 use crate::{
     marker::{BaseTag, BaseVersion},
     object::ObjID,
@@ -32,6 +34,8 @@ pub struct MetaInfo {
     pub tag: BaseTag,
     /// The version of the base struct type.
     pub version: BaseVersion,
+    /// A word reserved for advisory object locks. Zero when the object is unlocked.
+    pub lock: AtomicU64,
 }
 
 /// A tag for a meta extension entry.
@@ -9,7 +9,35 @@ pub const NULLPAGE_SIZE: usize = 0x1000;
 pub use twizzler_rt_abi::object::ObjID;
 use twizzler_rt_abi::object::{MapError, MapFlags};
 
-use crate::syscall::ObjectMapError;
+use crate::syscall::{ObjectMapError, ObjectReadMapError};
+
+/// The null object ID. No object has this ID.
+pub const NULL_ID: ObjID = ObjID::new(0);
+
+/// Check if `id` is the null object ID.
+pub fn is_null_id(id: ObjID) -> bool {
+    id == NULL_ID
+}
+
+// One step of splitmix64.
+fn mix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Derive a child object ID from `id` and `tag`, for example to name the objects that make up a
+/// larger structure. The same inputs always produce the same ID, and different tags produce
+/// different IDs. The derivation is a simple hash, and is not cryptographic: anyone who knows
+/// `id` and `tag` can compute the derived ID, and collisions with other IDs are possible, if
+/// unlikely.
+pub fn derive_id(id: ObjID, tag: u64) -> ObjID {
+    let [hi, lo] = id.parts();
+    let hi = mix64(hi ^ mix64(tag));
+    let lo = mix64(lo ^ mix64(hi ^ tag.rotate_left(32)));
+    ObjID::from_parts([hi, lo])
+}
 
 bitflags::bitflags! {
     /// Mapping protections for mapping objects into the address space.
@@ -59,8 +87,14 @@ impl From<MapFlags> for Protections {
 }
 
 impl From<MapFlags> for crate::syscall::MapFlags {
-    fn from(_value: MapFlags) -> Self {
-        Self::empty()
+    fn from(value: MapFlags) -> Self {
+        let mut f = Self::empty();
+        if value.contains(MapFlags::PERSIST) {
+            f.insert(crate::syscall::MapFlags::PERSIST);
+        }
+        // The runtime MapFlags come from the rt-abi submodule, which has no NOCACHE flag yet, so
+        // runtime mappings cannot ask for syscall::MapFlags::NOCACHE until one is added there.
+        f
     }
 }
 
@@ -75,3 +109,36 @@ impl Into<MapError> for ObjectMapError {
         }
     }
 }
+
+impl Into<MapError> for ObjectReadMapError {
+    fn into(self) -> MapError {
+        match self {
+            ObjectReadMapError::Unknown => MapError::Other,
+            ObjectReadMapError::InvalidSlot => MapError::InvalidArgument,
+            ObjectReadMapError::InvalidArgument => MapError::InvalidArgument,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{derive_id, is_null_id, ObjID, NULL_ID};
+
+    #[test]
+    fn derive_is_deterministic() {
+        let id = ObjID::from_parts([0x1234, 0x5678]);
+        assert_eq!(derive_id(id, 1), derive_id(id, 1));
+        assert_ne!(derive_id(id, 1), id);
+        assert!(!is_null_id(derive_id(NULL_ID, 0)));
+    }
+
+    #[test]
+    fn derive_tags_differ() {
+        let id = ObjID::from_parts([0x1234, 0x5678]);
+        let other = ObjID::from_parts([0x1234, 0x5679]);
+        for tag in 0..64 {
+            assert_ne!(derive_id(id, tag), derive_id(id, tag + 1));
+            assert_ne!(derive_id(id, tag), derive_id(other, tag));
+        }
+    }
+}
@@ -28,6 +28,35 @@ impl SysInfo {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Default, Hash)]
+#[repr(C)]
+/// Information about a single CPU.
+pub struct CpuInfo {
+    /// The ID of this CPU, as used for thread affinity.
+    pub id: u32,
+    /// The cluster this CPU belongs to: the index of the top-level node above it in the kernel's
+    /// CPU topology, such as a shared cache or a package. CPUs in the same cluster share that
+    /// node, but not necessarily a core. This is 0 if the topology is not yet known.
+    pub cluster: u32,
+}
+
+/// Read information about the CPUs on this system into `cpus`, ordered by CPU ID. Returns the total
+/// number of CPUs, which may be larger than the length of `cpus`, in which case only the first
+/// `cpus.len()` are filled in. Returns 0 if the kernel does not support this call.
+pub fn sys_cpu_info(cpus: &mut [CpuInfo]) -> usize {
+    let (code, val) = unsafe {
+        raw_syscall(
+            Syscall::CpuInfo,
+            &[cpus.as_mut_ptr() as u64, cpus.len() as u64],
+        )
+    };
+    if code != 0 {
+        0
+    } else {
+        val as usize
+    }
+}
+
 /// Get a SysInfo struct from the kernel.
 pub fn sys_info() -> SysInfo {
     let mut sysinfo = core::mem::MaybeUninit::<SysInfo>::zeroed();
@@ -48,6 +48,12 @@ bitflags! {
     /// Flags to pass to [sys_object_map].
     #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
     pub struct MapFlags: u32 {
+        /// The mapping participates in durability. Persistent mappings are the ones whose writes
+        /// are flushed by an object sync ([ObjectControlCmd::Sync](super::ObjectControlCmd::Sync)).
+        const PERSIST = 1;
+        /// Hint that the mapping is used for streaming access, and page caching should be bypassed
+        /// or minimized.
+        const NOCACHE = 2;
     }
 }
 
@@ -60,6 +60,8 @@ pub enum Syscall {
     SctxAttach,
     /// Gets random bytes
     GetRandom,
+    /// Read information about the system's CPUs.
+    CpuInfo,
     NumSyscalls,
 }
 
@@ -94,6 +94,9 @@ pub enum ThreadSpawnError {
     /// A specified object (handle) was not found.
     #[error("object handle not found")]
     NotFound = 2,
+    /// The caller may not act on the specified thread.
+    #[error("permission denied")]
+    PermissionDenied = 3,
 }
 
 /// Spawn a new thread, returning the ObjID of the thread's handle or an error.
@@ -1,6 +1,8 @@
-use num_enum::{FromPrimitive, IntoPrimitive};
+use core::time::Duration;
 
-use super::Syscall;
+use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
+
+use super::{convert_codes_to_result, Syscall, ThreadSpawnError};
 use crate::{
     arch::syscall::raw_syscall,
     object::ObjID,
@@ -59,6 +61,37 @@ pub enum ThreadControl {
     GetSelfId = 17,
     /// Get the ID of the active security context.
     GetActiveSctxId = 18,
+    /// Get the CPU time a thread has used since it started, in nanoseconds.
+    GetCpuTime = 19,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    TryFromPrimitive,
+    IntoPrimitive,
+)]
+#[repr(u64)]
+/// Coarse scheduling priority for a thread, ordered from least to most urgent.
+pub enum ThreadPriority {
+    /// Only run when nothing else wants the CPU.
+    Idle = 0,
+    /// Background work.
+    Low = 1,
+    /// The default for user threads.
+    #[default]
+    Normal = 2,
+    /// Latency-sensitive user threads.
+    High = 3,
+    /// Runs ahead of all non-realtime threads.
+    Realtime = 4,
 }
 
 /// Exit the thread. The code will be written to the [crate::thread::ThreadRepr] for the current
@@ -136,6 +169,65 @@ pub unsafe fn sys_thread_resume_from_upcall(frame: &UpcallFrame) -> ! {
     }
 }
 
+fn thread_ctrl_target(
+    target: Option<ObjID>,
+    cmd: ThreadControl,
+    arg: u64,
+) -> Result<u64, ThreadSpawnError> {
+    let ids = target.unwrap_or(ObjID::new(0)).parts();
+    let (code, val) =
+        unsafe { raw_syscall(Syscall::ThreadCtrl, &[cmd as u64, arg, ids[0], ids[1]]) };
+    convert_codes_to_result(
+        code,
+        val,
+        |c, _| c != 0,
+        |_, v| v,
+        |_, v| ThreadSpawnError::from(v),
+    )
+}
+
+/// Pin a thread to a single CPU, or allow it to run anywhere if `cpu` is None. If `target` is
+/// None, the calling thread is changed. Returns [ThreadSpawnError::InvalidArgument] if the CPU
+/// does not exist or is not running, [ThreadSpawnError::NotFound] if the target thread does not
+/// exist, or [ThreadSpawnError::PermissionDenied] if the target thread is not in the caller's
+/// address space.
+pub fn sys_thread_set_affinity(
+    target: Option<ObjID>,
+    cpu: Option<u32>,
+) -> Result<(), ThreadSpawnError> {
+    let arg = cpu.map_or(u64::MAX, |cpu| cpu as u64);
+    thread_ctrl_target(target, ThreadControl::SetAffinity, arg).map(|_| ())
+}
+
+/// Get the CPU that a thread is pinned to, if any. If `target` is None, the calling thread is
+/// queried.
+pub fn sys_thread_get_affinity(target: Option<ObjID>) -> Result<Option<u32>, ThreadSpawnError> {
+    thread_ctrl_target(target, ThreadControl::GetAffinity, 0)
+        .map(|cpu| (cpu != u64::MAX).then_some(cpu as u32))
+}
+
+/// Set a thread's priority. If `target` is None, the calling thread is changed. Only threads in
+/// the caller's address space may be changed, and only to a priority no higher than the default or
+/// the caller's own, whichever is higher. Otherwise, returns [ThreadSpawnError::PermissionDenied].
+pub fn sys_thread_set_priority(
+    target: Option<ObjID>,
+    priority: ThreadPriority,
+) -> Result<(), ThreadSpawnError> {
+    thread_ctrl_target(target, ThreadControl::SetPriority, priority.into()).map(|_| ())
+}
+
+/// Get a thread's priority. If `target` is None, the calling thread is queried.
+pub fn sys_thread_get_priority(target: Option<ObjID>) -> Result<ThreadPriority, ThreadSpawnError> {
+    let prio = thread_ctrl_target(target, ThreadControl::GetPriority, 0)?;
+    ThreadPriority::try_from(prio).map_err(|_| ThreadSpawnError::Unknown)
+}
+
+/// Get the CPU time a thread has used since it started. If `target` is None, the calling thread is
+/// queried.
+pub fn sys_thread_get_cpu_time(target: Option<ObjID>) -> Result<Duration, ThreadSpawnError> {
+    thread_ctrl_target(target, ThreadControl::GetCpuTime, 0).map(Duration::from_nanos)
+}
+
 pub fn sys_thread_ctrl(
     target: Option<ObjID>,
     cmd: ThreadControl,
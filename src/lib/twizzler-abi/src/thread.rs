@@ -84,6 +84,7 @@ impl From<ThreadSpawnError> for SpawnError {
             ThreadSpawnError::Unknown => Self::Other,
             ThreadSpawnError::InvalidArgument => Self::InvalidArgument,
             ThreadSpawnError::NotFound => Self::ObjectNotFound,
+            ThreadSpawnError::PermissionDenied => Self::InvalidArgument,
         }
     }
 }
@@ -3,14 +3,16 @@ use std::ptr::NonNull;
 use twizzler_abi::{meta::MetaInfo, object::NULLPAGE_SIZE};
 
 use crate::{
-    marker::{BaseType, BaseVersion, ObjSafe},
+    marker::{BaseTag, BaseType, BaseVersion, ObjSafe},
     object::Object,
 };
 
 /// Possible errors from getting a reference to an object's base.
 #[derive(Debug)]
 pub enum BaseError {
+    /// The object's stored base tag is not one of the tags accepted by the base type.
     InvalidTag,
+    /// The object's stored base tag is accepted, but the stored version (contained here) is not.
     InvalidVersion(BaseVersion),
 }
 
@@ -19,6 +21,17 @@ fn match_tags(_meta: NonNull<MetaInfo>) -> Result<(), BaseError> {
     Ok(())
 }
 
+fn check_tags<B: BaseType>(tag: BaseTag, version: BaseVersion) -> Result<(), BaseError> {
+    let tags = B::tags();
+    if tags.contains(&(version, tag)) {
+        Ok(())
+    } else if tags.iter().any(|(_, t)| *t == tag) {
+        Err(BaseError::InvalidVersion(version))
+    } else {
+        Err(BaseError::InvalidTag)
+    }
+}
+
 impl<T: BaseType + ObjSafe> Object<T> {
     /// Get a reference to the base of an object. Checks to see if the tags and version information
     /// for the BaseType match.
@@ -29,6 +42,18 @@ impl<T: BaseType + ObjSafe> Object<T> {
     }
 }
 
+impl<T> Object<T> {
+    /// Get a reference to the base of an object, interpreted as type B. The tag and version stored
+    /// in the object's metadata are compared against the accepted pairs in `B::tags()`, so a base
+    /// with a different layout (for example, one written before a schema change) is reported as an
+    /// error instead of being misinterpreted.
+    pub fn try_base<B: BaseType + ObjSafe>(&self) -> Result<&B, BaseError> {
+        let meta = unsafe { self.meta().as_ref() };
+        check_tags::<B>(meta.tag, meta.version)?;
+        Ok(unsafe { self.slot.raw_lea::<B>(NULLPAGE_SIZE).as_ref().unwrap() })
+    }
+}
+
 impl<BaseType> Object<BaseType> {
     /// Get a reference to the base of an object, bypassing version and tag checks.
     ///
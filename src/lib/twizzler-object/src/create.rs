@@ -2,12 +2,13 @@ use std::mem::MaybeUninit;
 
 use twizzler_abi::{
     marker::BaseType,
-    object::{ObjID, Protections},
+    object::{ObjID, Protections, MAX_SIZE, NULLPAGE_SIZE},
     syscall::{
         sys_object_create, BackingType, CreateTieFlags, CreateTieSpec, LifetimeType, ObjectCreate,
         ObjectCreateError, ObjectCreateFlags, ObjectSource,
     },
 };
+use twizzler_rt_abi::object::{twz_rt_map_object, MapFlags, ObjectHandle};
 
 use crate::{
     init::{ObjectInitError, ObjectInitFlags},
@@ -43,12 +44,34 @@ impl CreateSpec {
         self
     }
 
+    /// Set the lifetime of the new object.
+    pub fn lifetime(&mut self, lifetime: LifetimeType) -> &mut Self {
+        self.lifetime = lifetime;
+        self
+    }
+
     /// Add a tie to another object.
     pub fn tie<T>(&mut self, other: &Object<T>, flags: CreateTieFlags) -> &mut Self {
-        self.ties.push(CreateTieSpec::new(other.id(), flags));
+        self.tie_to(other.id(), flags)
+    }
+
+    /// Add a tie to the object with ID `id`, which need not be mapped. The new object is deleted
+    /// when the object it is tied to goes away.
+    pub fn tie_to(&mut self, id: ObjID, flags: CreateTieFlags) -> &mut Self {
+        self.ties.push(CreateTieSpec::new(id, flags));
         self
     }
 
+    /// Get the ties that will be created for the new object.
+    pub fn ties(&self) -> &[CreateTieSpec] {
+        &self.ties
+    }
+
+    /// Get the creation specification passed to the kernel, alongside [CreateSpec::ties].
+    pub fn object_create(&self) -> ObjectCreate {
+        ObjectCreate::new(self.backing, self.lifetime, self.kuid, self.flags)
+    }
+
     /// Add a source for object creation.
     pub fn src<T>(&mut self, src: ObjectSource) -> &mut Self {
         self.srcs.push(src);
@@ -63,10 +86,16 @@ pub enum CreateError {
     Init(ObjectInitError),
 }
 
+/// Create an object from `spec`, and map it read-write through the runtime, without setting up a
+/// base.
+pub fn create_spec(spec: &CreateSpec) -> Result<ObjectHandle, CreateError> {
+    let id = Object::<()>::raw_create(spec).map_err(CreateError::Create)?;
+    twz_rt_map_object(id, MapFlags::READ | MapFlags::WRITE).map_err(|e| CreateError::Init(e.into()))
+}
+
 impl<T> Object<T> {
     fn raw_create(spec: &CreateSpec) -> Result<ObjID, ObjectCreateError> {
-        let oc = ObjectCreate::new(spec.backing, spec.lifetime, spec.kuid, spec.flags);
-        sys_object_create(oc, &spec.srcs, &spec.ties)
+        sys_object_create(spec.object_create(), &spec.srcs, spec.ties())
     }
 
     /// Create an object, setting up the initial value for the base in a closure.
@@ -87,6 +116,32 @@ impl<T> Object<T> {
         // TODO: delete if we fail to map
         Ok(unsafe { core::mem::transmute(obj) })
     }
+
+    /// Create a new object whose contents are a snapshot of this object's current contents (base,
+    /// data, and FOT), using the lifetime, backing, key, and ties from `spec`. Any sources already
+    /// in `spec` are applied before the copy, and so are overwritten by it.
+    ///
+    /// The FOT is copied verbatim, so invariant pointers in the snapshot resolve to the same
+    /// objects as they do in the source object. Referenced objects are shared, not deep-copied;
+    /// callers that need an independent copy of an object graph must snapshot each object and
+    /// rewrite the FOT entries themselves.
+    ///
+    /// The kernel does not copy any data up front. The snapshot shares the source's pages
+    /// copy-on-write, covering the base and FOT as well as the data, so creating a snapshot of a
+    /// large object is cheap. A page is duplicated only when one side first writes to it. The
+    /// source and the snapshot are independent after this returns.
+    pub fn snapshot(&self, spec: &CreateSpec) -> Result<Self, CreateError> {
+        let oc = ObjectCreate::new(spec.backing, spec.lifetime, spec.kuid, spec.flags);
+        let mut srcs = spec.srcs.clone();
+        srcs.push(ObjectSource::new_copy(self.id(), 0, 0, MAX_SIZE));
+        let id = sys_object_create(oc, &srcs, &spec.ties).map_err(CreateError::Create)?;
+        Self::init_id(
+            id,
+            Protections::READ | Protections::WRITE,
+            ObjectInitFlags::empty(),
+        )
+        .map_err(CreateError::Init)
+    }
 }
 
 impl<T: BaseType> Object<T> {
@@ -99,7 +154,7 @@ impl<T: BaseType> Object<T> {
             ObjectInitFlags::empty(),
         )
         .map_err(CreateError::Init)?;
-        let base_raw: *mut T = obj.raw_lea_mut(0);
+        let base_raw: *mut T = obj.raw_lea_mut(NULLPAGE_SIZE);
         unsafe {
             base_raw.write(T::init(args));
         }
@@ -107,4 +162,77 @@ impl<T: BaseType> Object<T> {
         // TODO: delete if we fail to map
         Ok(obj)
     }
+
+    /// Create an object whose base is `base`. The object's metadata is stamped with the first
+    /// (version, tag) pair from `T::tags()`, so [Object::try_base] accepts the new object as a T.
+    /// If `T::tags()` is empty, the metadata is left untouched.
+    pub fn create_with_base(spec: &CreateSpec, base: T) -> Result<Self, CreateError> {
+        let id = Self::raw_create(spec).map_err(CreateError::Create)?;
+        let obj = Self::init_id(
+            id,
+            Protections::READ | Protections::WRITE,
+            ObjectInitFlags::empty(),
+        )
+        .map_err(CreateError::Init)?;
+        let base_raw: *mut T = obj.raw_lea_mut(NULLPAGE_SIZE);
+        unsafe {
+            base_raw.write(base);
+            if let Some((version, tag)) = T::tags().first() {
+                let meta = obj.meta().as_mut();
+                meta.tag = *tag;
+                meta.version = *version;
+            }
+        }
+        // TODO: persistence barrier
+        // TODO: delete if we fail to map
+        Ok(obj)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use twizzler_abi::{
+        marker::{BaseTag, BaseVersion},
+        syscall::{BackingType, LifetimeType},
+    };
+
+    use super::{BaseType, CreateSpec};
+    use crate::Object;
+
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    impl BaseType for Point {
+        fn init<T>(_t: T) -> Self {
+            Point { x: 1, y: 2 }
+        }
+
+        fn tags() -> &'static [(BaseVersion, BaseTag)] {
+            &[
+                (BaseVersion::new(2), BaseTag::new(0x9017)),
+                (BaseVersion::new(1), BaseTag::new(0x9017)),
+            ]
+        }
+    }
+
+    #[test]
+    fn create_inits_base() {
+        let spec = CreateSpec::new(LifetimeType::Volatile, BackingType::Normal);
+        let obj = Object::<Point>::create(&spec, ()).unwrap();
+        // Point::init is not all zeros, so this only passes if init's value landed at the base.
+        let base = unsafe { obj.base_unchecked() };
+        assert_eq!((base.x, base.y), (1, 2));
+    }
+
+    #[test]
+    fn create_with_base() {
+        let spec = CreateSpec::new(LifetimeType::Volatile, BackingType::Normal);
+        let obj = Object::create_with_base(&spec, Point { x: 3, y: 4 }).unwrap();
+        assert_eq!(obj.meta_version(), BaseVersion::new(2));
+        assert_eq!(obj.meta_tag(), BaseTag::new(0x9017));
+        let base = obj.try_base::<Point>().unwrap();
+        assert_eq!((base.x, base.y), (3, 4));
+    }
 }
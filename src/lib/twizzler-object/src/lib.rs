@@ -25,6 +25,10 @@
 //! Thus we expect programmers to use the twizzler-nando crate to operate on object data most of the
 //! time. The unsafe functions in this crate are provided mostly for the implementation of the
 //! twizzler-nando crate.
+//!
+//! Code that shares an object without twizzler-nando can keep to single-writer XOR
+//! multiple-readers cooperatively, with the advisory locks provided by [Object::lock_shared] and
+//! [Object::lock_exclusive]. The kernel does not enforce these locks.
 
 #![feature(auto_traits)]
 //#![feature(specialization)]
@@ -35,6 +39,7 @@ pub use twizzler_abi::object::ObjID;
 mod base;
 mod create;
 mod init;
+mod lock;
 pub mod marker;
 pub mod meta;
 mod object;
@@ -43,4 +48,5 @@ pub mod slot;
 
 pub use create::*;
 pub use init::*;
+pub use lock::*;
 pub use object::*;
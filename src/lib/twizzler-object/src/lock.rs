@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use twizzler_abi::syscall::{
+    sys_thread_sync, ThreadSync, ThreadSyncFlags, ThreadSyncOp, ThreadSyncReference,
+    ThreadSyncSleep, ThreadSyncWake,
+};
+use twizzler_rt_abi::object::MapFlags;
+
+use crate::{meta::MetaError, Object};
+
+// Set in the lock word while the lock is held exclusively. Otherwise, the word counts the shared
+// holders.
+const EXCLUSIVE: u64 = 1 << 63;
+
+fn wait(word: &AtomicU64, cur: u64) {
+    let sleep = ThreadSync::new_sleep(ThreadSyncSleep::new(
+        ThreadSyncReference::Virtual(word),
+        cur,
+        ThreadSyncOp::Equal,
+        ThreadSyncFlags::empty(),
+    ));
+    let _ = sys_thread_sync(&mut [sleep], None);
+}
+
+fn wake_all(word: &AtomicU64) {
+    let wake = ThreadSync::new_wake(ThreadSyncWake::new(
+        ThreadSyncReference::Virtual(word),
+        usize::MAX,
+    ));
+    let _ = sys_thread_sync(&mut [wake], None);
+}
+
+/// A held advisory lock on an object, released when dropped. See [Object::lock_shared] and
+/// [Object::lock_exclusive].
+pub struct ObjectLockGuard<'a> {
+    word: &'a AtomicU64,
+    exclusive: bool,
+}
+
+impl ObjectLockGuard<'_> {
+    /// Whether this guard holds the lock exclusively.
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+}
+
+impl Drop for ObjectLockGuard<'_> {
+    fn drop(&mut self) {
+        if self.exclusive {
+            self.word.store(0, Ordering::Release);
+            wake_all(self.word);
+        } else if self.word.fetch_sub(1, Ordering::Release) == 1 {
+            // Only exclusive lockers can be waiting on shared holders.
+            wake_all(self.word);
+        }
+    }
+}
+
+impl<T> Object<T> {
+    // The lock word is the one reserved for this in the object's meta info.
+    fn lock_word(&self) -> Result<&AtomicU64, MetaError> {
+        if !self
+            .slot
+            .runtime_handle()
+            .map_flags()
+            .contains(MapFlags::WRITE)
+        {
+            return Err(MetaError::ReadOnly);
+        }
+        Ok(unsafe { &(*self.meta().as_ptr()).lock })
+    }
+
+    /// Take a shared lock on the object, waiting while anyone holds it exclusively. Any number of
+    /// shared locks may be held at once, from any compartment that maps the object.
+    ///
+    /// The lock is advisory: the kernel does not enforce it, so it only keeps out writers that
+    /// take the exclusive lock themselves. There is no fairness, so a steady stream of shared
+    /// lockers can starve exclusive ones. The object must be mapped writable, even for a shared
+    /// lock, or this returns Err([MetaError::ReadOnly]).
+    pub fn lock_shared(&self) -> Result<ObjectLockGuard<'_>, MetaError> {
+        let word = self.lock_word()?;
+        loop {
+            let cur = word.load(Ordering::Acquire);
+            if cur & EXCLUSIVE != 0 {
+                wait(word, cur);
+            } else if word
+                .compare_exchange_weak(cur, cur + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return Ok(ObjectLockGuard {
+                    word,
+                    exclusive: false,
+                });
+            }
+        }
+    }
+
+    /// Take an exclusive lock on the object, waiting until no one else holds it. See
+    /// [Object::lock_shared].
+    pub fn lock_exclusive(&self) -> Result<ObjectLockGuard<'_>, MetaError> {
+        let word = self.lock_word()?;
+        loop {
+            match word.compare_exchange_weak(0, EXCLUSIVE, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => {
+                    return Ok(ObjectLockGuard {
+                        word,
+                        exclusive: true,
+                    })
+                }
+                Err(0) => continue,
+                Err(cur) => wait(word, cur),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use twizzler_abi::syscall::{BackingType, LifetimeType};
+
+    use crate::{CreateSpec, Object};
+
+    fn new_counter() -> Object<u64> {
+        let spec = CreateSpec::new(LifetimeType::Volatile, BackingType::Normal);
+        Object::create_with(&spec, |_| {}).unwrap()
+    }
+
+    #[test]
+    fn exclusive_contention() {
+        let obj = new_counter();
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let obj = obj.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        let _guard = obj.lock_exclusive().unwrap();
+                        // A non-atomic read-modify-write, with a yield in the middle to invite
+                        // the other thread in. Only the lock keeps updates from being lost.
+                        let count = unsafe { obj.base_mut_unchecked() };
+                        let old = *count;
+                        thread::yield_now();
+                        *count = old + 1;
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(unsafe { *obj.base_unchecked() }, 2000);
+    }
+
+    #[test]
+    fn shared_blocks_exclusive() {
+        let obj = new_counter();
+        let shared = obj.lock_shared().unwrap();
+        assert!(!shared.is_exclusive());
+        // Shared locks do not exclude each other.
+        drop(obj.lock_shared().unwrap());
+
+        let acquired = Arc::new(AtomicBool::new(false));
+        let t = {
+            let obj = obj.clone();
+            let acquired = acquired.clone();
+            thread::spawn(move || {
+                let guard = obj.lock_exclusive().unwrap();
+                assert!(guard.is_exclusive());
+                acquired.store(true, Ordering::SeqCst);
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!acquired.load(Ordering::SeqCst));
+        drop(shared);
+        t.join().unwrap();
+        assert!(acquired.load(Ordering::SeqCst));
+    }
+}
@@ -5,6 +5,7 @@ use twizzler_abi::{
     meta::{MetaExt, MetaFlags, MetaInfo, Nonce},
     object::ObjID,
 };
+use twizzler_rt_abi::object::{MapFlags, ObjectHandle};
 
 use crate::Object;
 
@@ -31,14 +32,50 @@ pub struct FotEntry {
     resv: u32,
 }
 
+/// Possible errors from updating an object's metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaError {
+    /// The object is not mapped writable.
+    ReadOnly,
+}
+
+// The meta info struct sits halfway into the meta page, above the FOT.
+fn meta_info(meta_page: usize) -> *mut MetaInfo {
+    (meta_page + twizzler_abi::object::NULLPAGE_SIZE / 2) as *mut MetaInfo
+}
+
+/// Stamp a new base version and tag into an object's metadata. This is for migrating an object in
+/// place: after rewriting the base in the new layout, stamp the new version so that
+/// [Object::try_base] accepts it as a base type that lists the new (version, tag) pair, and
+/// rejects it as one that only lists the old pair. The handle must be mapped writable.
+pub fn set_base_version(
+    handle: &ObjectHandle,
+    version: BaseVersion,
+    tag: BaseTag,
+) -> Result<(), MetaError> {
+    if !handle.map_flags().contains(MapFlags::WRITE) {
+        return Err(MetaError::ReadOnly);
+    }
+    let meta = meta_info(handle.meta() as usize);
+    unsafe {
+        (*meta).version = version;
+        (*meta).tag = tag;
+    }
+    Ok(())
+}
+
 impl<T> Object<T> {
+    /// Stamp a new base version and tag into the object's metadata. See [set_base_version].
+    pub fn set_base_version(&self, version: BaseVersion, tag: BaseTag) -> Result<(), MetaError> {
+        set_base_version(self.slot.runtime_handle(), version, tag)
+    }
+
     /// Get a mutable reference to the object's meta info struct.
     ///
     /// # Safety
     /// See this crate's base documentation ([Isolation Safety](crate)).
     pub unsafe fn meta(&self) -> NonNull<MetaInfo> {
-        let end = self.slot.vaddr_meta();
-        ((end + twizzler_abi::object::NULLPAGE_SIZE / 2) as *mut MetaInfo)
+        meta_info(self.slot.vaddr_meta())
             .as_mut()
             .unwrap_unchecked()
             .into()
@@ -58,27 +95,27 @@ impl<T> Object<T> {
 
     /// Get the nonce of the object.
     pub fn meta_nonce(&self) -> Nonce {
-        unsafe { self.meta().as_mut().nonce }
+        unsafe { self.meta().as_ref().nonce }
     }
 
     /// Get the public key ID of the object.
     pub fn meta_kuid(&self) -> ObjID {
-        unsafe { self.meta().as_mut().kuid }
+        unsafe { self.meta().as_ref().kuid }
     }
 
     /// Get the meta flags of the object.
     pub fn meta_flags(&self) -> MetaFlags {
-        unsafe { self.meta().as_mut().flags }
+        unsafe { self.meta().as_ref().flags }
     }
 
     /// Get the base tag of the object.
     pub fn meta_tag(&self) -> BaseTag {
-        unsafe { self.meta().as_mut().tag }
+        unsafe { self.meta().as_ref().tag }
     }
 
     /// Get the base version of the object.
     pub fn meta_version(&self) -> BaseVersion {
-        unsafe { self.meta().as_mut().version }
+        unsafe { self.meta().as_ref().version }
     }
 
     /// Get a mutable pointer to one of the object's FOT entries.
@@ -90,3 +127,70 @@ impl<T> Object<T> {
         self.slot.get_fote_unguarded(idx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twizzler_abi::syscall::{BackingType, LifetimeType};
+
+    use super::MetaError;
+    use crate::{
+        base::BaseError,
+        marker::{BaseTag, BaseType, BaseVersion},
+        CreateSpec, Object, ObjectInitFlags, Protections,
+    };
+
+    const TAG: BaseTag = BaseTag::new(0x7e57_ba5e);
+
+    struct V1(u32);
+    impl BaseType for V1 {
+        fn init<T>(_t: T) -> Self {
+            V1(0)
+        }
+
+        fn tags() -> &'static [(BaseVersion, BaseTag)] {
+            &[(BaseVersion::new(1), TAG)]
+        }
+    }
+
+    struct V2(u64);
+    impl BaseType for V2 {
+        fn init<T>(_t: T) -> Self {
+            V2(0)
+        }
+
+        fn tags() -> &'static [(BaseVersion, BaseTag)] {
+            &[(BaseVersion::new(2), TAG)]
+        }
+    }
+
+    #[test]
+    fn base_version_round_trip() {
+        let spec = CreateSpec::new(LifetimeType::Volatile, BackingType::Normal);
+        let obj = Object::<V1>::create_with(&spec, |_| {}).unwrap();
+        assert!(matches!(obj.try_base::<V1>(), Err(BaseError::InvalidTag)));
+
+        obj.set_base_version(BaseVersion::new(1), TAG).unwrap();
+        assert_eq!(obj.meta_version(), BaseVersion::new(1));
+        assert_eq!(obj.meta_tag(), TAG);
+        assert!(obj.try_base::<V1>().is_ok());
+        assert!(matches!(
+            obj.try_base::<V2>(),
+            Err(BaseError::InvalidVersion(v)) if v == BaseVersion::new(1)
+        ));
+
+        // Migrate to the new schema.
+        obj.set_base_version(BaseVersion::new(2), TAG).unwrap();
+        assert!(obj.try_base::<V2>().is_ok());
+        assert!(matches!(
+            obj.try_base::<V1>(),
+            Err(BaseError::InvalidVersion(v)) if v == BaseVersion::new(2)
+        ));
+
+        let ro =
+            Object::<V2>::init_id(obj.id(), Protections::READ, ObjectInitFlags::empty()).unwrap();
+        assert_eq!(
+            ro.set_base_version(BaseVersion::new(3), TAG),
+            Err(MetaError::ReadOnly)
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::{marker::PhantomData, sync::Arc};
 
-use twizzler_abi::object::ObjID;
+use twizzler_abi::object::{ObjID, Protections};
+use twizzler_rt_abi::object::{MapError, MapFlags};
 
 use crate::slot::Slot;
 
@@ -30,6 +31,22 @@ impl<T> Object<T> {
         &self.slot
     }
 
+    /// Get the protections this object is actually mapped with, as reported by the kernel. See
+    /// [Slot::protections].
+    pub fn protections(&self) -> Result<Protections, MapError> {
+        self.slot.protections()
+    }
+
+    /// Map this object again with `flags`, for example to get a writable view of an object that
+    /// was opened read-only. The runtime caches mappings by object and flags, so if `flags` are
+    /// the ones this handle was mapped with, the new handle shares this handle's mapping. Either
+    /// way, each handle holds its own reference, and dropping one leaves the other mapped.
+    pub fn reopen(&self, flags: MapFlags) -> Result<Object<T>, MapError> {
+        let handle = twizzler_rt_abi::object::twz_rt_map_object(self.id(), flags)?;
+        let slot = Slot::new_from_handle(handle).map_err(|_| MapError::Other)?;
+        Ok(Arc::new(slot).into())
+    }
+
     /// Transmute the object of base type T to base type N.
     ///
     /// # Safety
@@ -47,3 +64,38 @@ impl<Base> From<Arc<Slot>> for Object<Base> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twizzler_abi::syscall::{BackingType, LifetimeType};
+    use twizzler_rt_abi::object::MapFlags;
+
+    use crate::{CreateSpec, Object, Protections};
+
+    #[test]
+    fn reopen() {
+        let spec = CreateSpec::new(LifetimeType::Volatile, BackingType::Normal);
+        let obj = Object::<u64>::create_with(&spec, |_| {}).unwrap();
+        unsafe { *obj.base_mut_unchecked() = 42 };
+
+        // The same flags get the mapping the runtime already has.
+        let same = obj.reopen(MapFlags::READ | MapFlags::WRITE).unwrap();
+        assert!(std::ptr::eq(unsafe { same.base_unchecked() }, unsafe {
+            obj.base_unchecked()
+        }));
+
+        // Different flags get a mapping of their own, of the same object.
+        let ro = obj.reopen(MapFlags::READ).unwrap();
+        assert_eq!(ro.id(), obj.id());
+        assert!(!std::ptr::eq(unsafe { ro.base_unchecked() }, unsafe {
+            obj.base_unchecked()
+        }));
+        assert!(!ro.protections().unwrap().contains(Protections::WRITE));
+        assert_eq!(unsafe { *ro.base_unchecked() }, 42);
+
+        // Dropping the original leaves the shared mapping in place for the other handle.
+        drop(obj);
+        unsafe { *same.base_mut_unchecked() = 43 };
+        assert_eq!(unsafe { *ro.base_unchecked() }, 43);
+    }
+}
@@ -4,7 +4,10 @@ use std::{
     sync::{Arc, Mutex, Weak},
 };
 
-use twizzler_abi::object::{ObjID, Protections, MAX_SIZE, NULLPAGE_SIZE};
+use twizzler_abi::{
+    object::{ObjID, Protections, MAX_SIZE, NULLPAGE_SIZE},
+    syscall::sys_object_read_map,
+};
 use twizzler_rt_abi::object::{MapError, MapFlags, ObjectHandle};
 
 use crate::{meta::FotEntry, ObjectInitError};
@@ -100,6 +103,17 @@ impl Slot {
         self.prot
     }
 
+    /// Ask the kernel for the protections this slot's object is actually mapped with. These may be
+    /// narrower than [Slot::prot], for example if WRITE was requested for an object that cannot be
+    /// written.
+    pub fn protections(&self) -> Result<Protections, MapError> {
+        let info = sys_object_read_map(None, self.slot_number()).map_err(|e| e.into())?;
+        if info.id != self.id {
+            return Err(MapError::NoSuchObject);
+        }
+        Ok(info.prot)
+    }
+
     /// Get the vaddr of this slot's object base.
     pub fn vaddr_base(&self) -> usize {
         self.vaddr_null() + NULLPAGE_SIZE
@@ -114,6 +114,18 @@ impl<T> QueueEntry<T> {
         self.data
     }
 
+    #[inline]
+    /// Get a reference to the data item of a QueueEntry.
+    pub fn item_ref(&self) -> &T {
+        &self.data
+    }
+
+    #[inline]
+    /// Get a reference to the data item of a QueueEntry. The same as [QueueEntry::item_ref].
+    pub fn data_ref(&self) -> &T {
+        self.item_ref()
+    }
+
     #[inline]
     /// Get the info tag of a QueueEntry.
     pub fn info(&self) -> u32 {
@@ -131,6 +143,19 @@ impl<T> QueueEntry<T> {
     }
 }
 
+/// The stride of a queue holding entries of type T, for use when constructing a [RawQueueHdr].
+///
+/// ```
+/// # use twizzler_queue_raw::{entry_stride, QueueEntry, RawQueue, RawQueueHdr};
+/// let hdr = RawQueueHdr::new(4, entry_stride::<u64>());
+/// let mut buffer = [QueueEntry::<u64>::default(); 1 << 4];
+/// let queue = unsafe { RawQueue::new_checked(&hdr, buffer.as_mut_ptr()) };
+/// assert!(queue.is_ok());
+/// ```
+pub const fn entry_stride<T>() -> usize {
+    core::mem::size_of::<QueueEntry<T>>()
+}
+
 /// The base info structure stored in a Twizzler queue object. Used to open Twizzler queue objects
 /// and create a [Queue].
 #[repr(C)]
@@ -165,11 +190,34 @@ pub struct RawQueueHdr {
     waiters: AtomicU32,
     bell: AtomicU64,
     tail: AtomicU64,
+    spin_limit: u32,
 }
 
+/// The number of times submitters and consumers spin before sleeping, unless configured otherwise.
+pub const DEFAULT_SPIN_LIMIT: u32 = 1000;
+
+/// The largest supported log2 of a queue's length. The queue counters are 31 bits wide (the top
+/// bit is a waiting flag), and the turn computation needs each counter to wrap after an even
+/// number of laps around the queue.
+pub const MAX_L2LEN: usize = 30;
+
+// The queue counters are taken modulo 2^31.
+const COUNTER_MASK: u32 = 0x7fffffff;
+
 impl RawQueueHdr {
     /// Construct a new raw queue header.
     pub fn new(l2len: usize, stride: usize) -> Self {
+        Self::new_with_spin(l2len, stride, DEFAULT_SPIN_LIMIT)
+    }
+
+    /// Construct a new raw queue header that spins at most `spin_limit` times waiting for the
+    /// queue before calling the wait callback. Small values suit callers for whom spinning is
+    /// expensive, and larger values suit latency-sensitive queues.
+    ///
+    /// # Panics
+    /// Panics if `l2len` is larger than [MAX_L2LEN].
+    pub fn new_with_spin(l2len: usize, stride: usize, spin_limit: u32) -> Self {
+        assert!(l2len <= MAX_L2LEN, "queue length 2^{} is too large", l2len);
         Self {
             l2len,
             stride,
@@ -177,6 +225,7 @@ impl RawQueueHdr {
             waiters: AtomicU32::new(0),
             bell: AtomicU64::new(0),
             tail: AtomicU64::new(0),
+            spin_limit,
         }
     }
 
@@ -185,9 +234,11 @@ impl RawQueueHdr {
         1 << self.l2len
     }
 
+    // The distance from tail to head is computed modulo 2^31, so that it stays correct once the
+    // head has wrapped and the tail has not yet.
     #[inline]
     fn is_full(&self, h: u32, t: u64) -> bool {
-        (h & 0x7fffffff) as u64 - (t & 0x7fffffff) >= self.len() as u64
+        (h.wrapping_sub(t as u32) & COUNTER_MASK) as usize >= self.len()
     }
 
     #[inline]
@@ -231,6 +282,11 @@ impl RawQueueHdr {
         self.waiters.fetch_sub(1, Ordering::SeqCst);
     }
 
+    // Reserving takes a ticket from head, and a ticket's slot is free once the tail is within
+    // len() of it. The tail only moves forward, so slots free up strictly in ticket order, and
+    // a submitter can only be overtaken by submitters holding earlier tickets. Spinning and
+    // sleeping only affect how quickly a waiter notices that its slot is free, not whether a later
+    // submitter gets it first, so waiting submitters cannot starve.
     #[inline]
     fn reserve_slot<W: Fn(&AtomicU64, u64)>(
         &self,
@@ -239,7 +295,7 @@ impl RawQueueHdr {
     ) -> Result<u32, QueueError> {
         let h = self.head.fetch_add(1, Ordering::SeqCst);
         let mut waiter = false;
-        let mut attempts = 1000;
+        let mut attempts = self.spin_limit;
         loop {
             let t = self.tail.load(Ordering::SeqCst);
             if !self.is_full(h, t) {
@@ -274,6 +330,70 @@ impl RawQueueHdr {
         Ok(h & 0x7fffffff)
     }
 
+    // Like reserve_slot, but gives up once the deadline passes. The head is only advanced with a
+    // compare-exchange once the slot is known to be free, rather than up front, so giving up never
+    // leaves behind a reserved slot that will not be filled. The tail only moves forward, so a
+    // free slot stays free until we claim it.
+    #[cfg(any(feature = "std", test))]
+    fn reserve_slot_deadline<W: Fn(&AtomicU64, u64, core::time::Duration) -> bool>(
+        &self,
+        flags: SubmissionFlags,
+        deadline: std::time::Instant,
+        wait: W,
+    ) -> Result<u32, QueueError> {
+        let mut waiter = false;
+        let mut attempts = self.spin_limit;
+        let res = loop {
+            let h = self.head.load(Ordering::SeqCst);
+            let t = self.tail.load(Ordering::SeqCst);
+            if !self.is_full(h, t) {
+                match self.head.compare_exchange(
+                    h,
+                    h.wrapping_add(1),
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                ) {
+                    Ok(_) => break Ok(h & 0x7fffffff),
+                    // Another submitter claimed this slot first, so try the next one.
+                    Err(_) => continue,
+                }
+            }
+
+            if flags.contains(SubmissionFlags::NON_BLOCK) {
+                break Err(QueueError::WouldBlock);
+            }
+
+            let remaining = match deadline.checked_duration_since(std::time::Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => remaining,
+                _ => break Err(QueueError::TimedOut),
+            };
+
+            if attempts != 0 {
+                attempts -= 1;
+                core::hint::spin_loop();
+                continue;
+            }
+
+            if !waiter {
+                waiter = true;
+                self.inc_submit_waiting();
+            }
+
+            let h = self.head.load(Ordering::SeqCst);
+            let t = self.tail.load(Ordering::SeqCst);
+            if self.is_full(h, t) {
+                // Whether or not the wait timed out, the loop re-checks the queue and the deadline.
+                let _ = wait(&self.tail, t, remaining);
+            }
+        };
+
+        if waiter {
+            self.dec_submit_waiting();
+        }
+
+        res
+    }
+
     #[inline]
     fn get_turn(&self, h: u32) -> bool {
         (h / self.len() as u32) % 2 == 0
@@ -294,7 +414,7 @@ impl RawQueueHdr {
         flags: ReceiveFlags,
         raw_buf: *const QueueEntry<T>,
     ) -> Result<u64, QueueError> {
-        let mut attempts = 1000;
+        let mut attempts = self.spin_limit;
         let t = self.tail.load(Ordering::SeqCst) & 0x7fffffff;
         loop {
             let b = self.bell.load(Ordering::SeqCst);
@@ -371,6 +491,22 @@ impl RawQueueHdr {
         }
     }
 
+    #[inline]
+    fn receive_ready<T>(&self, raw_buf: *const QueueEntry<T>) -> bool {
+        let t = self.tail.load(Ordering::SeqCst) & 0x7fffffff;
+        let b = self.bell.load(Ordering::SeqCst);
+        let item = unsafe { raw_buf.add((t as usize) & (self.len() - 1)) };
+        !self.is_empty(b, t) && self.is_turn(t, item)
+    }
+
+    #[cfg(any(feature = "std", test))]
+    #[inline]
+    fn submit_ready(&self) -> bool {
+        let t = self.tail.load(Ordering::SeqCst) & 0x7fffffff;
+        let h = self.head.load(Ordering::SeqCst) & 0x7fffffff;
+        !self.is_full(h, t)
+    }
+
     #[inline]
     fn advance_tail<R: Fn(&AtomicU64)>(&self, ring: R) {
         let t = self.tail.load(Ordering::SeqCst);
@@ -380,6 +516,19 @@ impl RawQueueHdr {
         }
     }
 
+    fn drain<T, R: Fn(&AtomicU64)>(&self, raw_buf: *const QueueEntry<T>, ring: R) -> usize {
+        let mut count = 0;
+        while self.receive_ready(raw_buf) {
+            let t = self.tail.load(Ordering::SeqCst);
+            self.tail.store((t + 1) & 0x7fffffff, Ordering::SeqCst);
+            count += 1;
+        }
+        if count > 0 && self.submitter_waiting() {
+            ring(&self.tail);
+        }
+        count
+    }
+
     #[inline]
     fn advance_tail_setup<'a>(&'a self, ringer: &mut Option<&'a AtomicU64>) {
         let t = self.tail.load(Ordering::SeqCst);
@@ -398,12 +547,14 @@ pub struct RawQueue<T> {
 
 bitflags::bitflags! {
     /// Flags to control how queue submission works.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct SubmissionFlags: u32 {
         /// If the request would block, return Err([SubmissionError::WouldBlock]) instead.
         const NON_BLOCK = 1;
     }
 
     /// Flags to control how queue receive works.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct ReceiveFlags: u32 {
         /// If the request would block, return Err([ReceiveError::WouldBlock]) instead.
         const NON_BLOCK = 1;
@@ -417,6 +568,10 @@ pub enum QueueError {
     Unknown,
     /// The operation would have blocked, and non-blocking operation was specified.
     WouldBlock,
+    /// The queue header's stride does not match the size of the queue's entries.
+    StrideMismatch,
+    /// The operation did not complete before its deadline.
+    TimedOut,
 }
 
 impl Display for QueueError {
@@ -424,6 +579,8 @@ impl Display for QueueError {
         match self {
             Self::Unknown => write!(f, "unknown"),
             Self::WouldBlock => write!(f, "would block"),
+            Self::StrideMismatch => write!(f, "stride mismatch"),
+            Self::TimedOut => write!(f, "timed out"),
         }
     }
 }
@@ -435,6 +592,7 @@ impl From<QueueError> for std::io::Error {
     fn from(err: QueueError) -> Self {
         match err {
             QueueError::WouldBlock => std::io::Error::from(std::io::ErrorKind::WouldBlock),
+            QueueError::TimedOut => std::io::Error::from(std::io::ErrorKind::TimedOut),
             _ => std::io::Error::from(std::io::ErrorKind::Other),
         }
     }
@@ -442,6 +600,11 @@ impl From<QueueError> for std::io::Error {
 
 impl<T: Copy> RawQueue<T> {
     /// Construct a new raw queue out of a header reference and a buffer pointer.
+    ///
+    /// The header's stride must equal `size_of::<QueueEntry<T>>()`. This is not checked, and a
+    /// mismatch silently corrupts indexing into the buffer. Use [RawQueue::new_checked] unless this
+    /// is a hot path where the header is known to be correct.
+    ///
     /// # Safety
     /// The caller must ensure that hdr and buf point to valid objects, and that the lifetime of the
     /// RawQueue is exceeded by the objects pointed to.
@@ -452,6 +615,23 @@ impl<T: Copy> RawQueue<T> {
         }
     }
 
+    /// Construct a new raw queue out of a header reference and a buffer pointer, returning
+    /// Err([QueueError::StrideMismatch]) if the header's stride does not equal
+    /// `size_of::<QueueEntry<T>>()`.
+    ///
+    /// # Safety
+    /// The caller must ensure that hdr and buf point to valid objects, and that the lifetime of the
+    /// RawQueue is exceeded by the objects pointed to.
+    pub unsafe fn new_checked(
+        hdr: *const RawQueueHdr,
+        buf: *mut QueueEntry<T>,
+    ) -> Result<Self, QueueError> {
+        if (*hdr).stride != entry_stride::<T>() {
+            return Err(QueueError::StrideMismatch);
+        }
+        Ok(Self::new(hdr, buf))
+    }
+
     #[inline]
     fn hdr(&self) -> &RawQueueHdr {
         unsafe { &*self.hdr }
@@ -491,6 +671,37 @@ impl<T: Copy> RawQueue<T> {
         Ok(())
     }
 
+    /// Like [RawQueue::submit], but gives up with Err([QueueError::TimedOut]) if no slot frees up
+    /// within `timeout`. The wait callback is called as wait(x, y, remaining), and should sleep
+    /// until *x != y or until `remaining` has passed, returning false if it timed out.
+    ///
+    /// A submit that times out has not claimed a slot, so it leaves the queue exactly as it found
+    /// it, and the consumer never waits on an entry that will not arrive. Because of this, a slot
+    /// is only claimed once it is free, which means a deadline submit can lose a race for a newly
+    /// freed slot to other submitters.
+    #[cfg(any(feature = "std", test))]
+    pub fn submit_deadline<
+        W: Fn(&AtomicU64, u64, core::time::Duration) -> bool,
+        R: Fn(&AtomicU64),
+    >(
+        &self,
+        item: QueueEntry<T>,
+        wait: W,
+        ring: R,
+        flags: SubmissionFlags,
+        timeout: core::time::Duration,
+    ) -> Result<(), QueueError> {
+        let deadline = std::time::Instant::now() + timeout;
+        let h = self.hdr().reserve_slot_deadline(flags, deadline, wait)?;
+        let buf_item = self.get_buf(h as usize);
+        *buf_item = item;
+        let turn = self.hdr().get_turn(h);
+        buf_item.set_cmd_slot(h | if turn { 1u32 << 31 } else { 0 });
+
+        self.hdr().ring(ring);
+        Ok(())
+    }
+
     /// Receive data from the queue, returning either that data or an error. The wait and ring
     /// callbacks work similar to [RawQueue::submit].
     pub fn receive<W: Fn(&AtomicU64, u64), R: Fn(&AtomicU64)>(
@@ -508,6 +719,42 @@ impl<T: Copy> RawQueue<T> {
         Ok(item)
     }
 
+    /// Receive data from the queue without copying it out of the ring. Once an entry is ready, `f`
+    /// is called with a reference to the entry in place, and the slot is released only after `f`
+    /// returns. The wait and ring callbacks work similar to [RawQueue::submit].
+    ///
+    /// The slot stays held for as long as `f` runs, so submitters may fill the queue and block
+    /// behind it. `f` must not block indefinitely, and must not call back into this queue.
+    pub fn receive_ref<
+        W: Fn(&AtomicU64, u64),
+        R: Fn(&AtomicU64),
+        F: FnOnce(&QueueEntry<T>) -> Out,
+        Out,
+    >(
+        &self,
+        wait: W,
+        ring: R,
+        flags: ReceiveFlags,
+        f: F,
+    ) -> Result<Out, QueueError> {
+        let t = self
+            .hdr()
+            .get_next_ready(wait, flags, unsafe { *self.buf.get() })?;
+        let out = f(self.get_buf(t as usize));
+        self.hdr().advance_tail(ring);
+        Ok(out)
+    }
+
+    /// Discard all entries that are currently ready in the queue without processing them, returning
+    /// the number of entries discarded. Entries submitted concurrently may or may not be discarded.
+    /// If any entries were discarded and a submitter is waiting for space, ring is called once to
+    /// wake it up.
+    ///
+    /// Like [RawQueue::receive], this must only be called by the queue's single consumer.
+    pub fn drain<R: Fn(&AtomicU64)>(&self, ring: R) -> usize {
+        self.hdr().drain(unsafe { *self.buf.get() }, ring)
+    }
+
     pub fn setup_sleep<'a>(
         &'a self,
         sleep: bool,
@@ -539,6 +786,109 @@ impl<T: Copy> RawQueue<T> {
 unsafe impl<T: Send> Send for RawQueue<T> {}
 unsafe impl<T: Send> Sync for RawQueue<T> {}
 
+#[cfg(any(feature = "std", test))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WaitDirection {
+    Receive,
+    Submit,
+}
+
+#[cfg(any(feature = "std", test))]
+/// A condvar-like helper that wraps the raw sleep setup primitives ([RawQueue::setup_sleep_simple]
+/// and [RawQueue::setup_send_sleep_simple]) for a single queue.
+///
+/// The platform-specific sleep is supplied as a callback to [QueueWaiter::wait], in the same style
+/// as the wait callbacks for [RawQueue::submit] and [RawQueue::receive]. The waiter re-checks the
+/// queue's empty/full and turn conditions after setting up the sleep and after every wakeup, so a
+/// wakeup that races with going to sleep is not lost.
+pub struct QueueWaiter<'a, T> {
+    queue: &'a RawQueue<T>,
+    dir: WaitDirection,
+}
+
+#[cfg(any(feature = "std", test))]
+impl<'a, T: Copy> QueueWaiter<'a, T> {
+    /// Make a waiter that waits until data may be ready to receive from the queue. Like
+    /// [RawQueue::receive], this must only be used by the queue's single consumer.
+    pub fn for_receive(queue: &'a RawQueue<T>) -> Self {
+        Self {
+            queue,
+            dir: WaitDirection::Receive,
+        }
+    }
+
+    /// Make a waiter that waits until there may be space to submit to the queue.
+    pub fn for_submit(queue: &'a RawQueue<T>) -> Self {
+        Self {
+            queue,
+            dir: WaitDirection::Submit,
+        }
+    }
+
+    /// Check if the condition this waiter waits for currently holds.
+    pub fn is_ready(&self) -> bool {
+        let hdr = self.queue.hdr();
+        match self.dir {
+            WaitDirection::Receive => hdr.receive_ready(unsafe { *self.queue.buf.get() }),
+            WaitDirection::Submit => hdr.submit_ready(),
+        }
+    }
+
+    /// Block until the queue may be ready, or until the timeout expires. Returns true if the queue
+    /// became ready, and false on timeout. Note that readiness is a hint: a concurrent submitter
+    /// may still take the free space before we do.
+    ///
+    /// The wait callback is called as wait(x, y, timeout), and should sleep until *x != y or
+    /// until the supplied timeout (if any) expires, returning false if it timed out.
+    pub fn wait<W: Fn(&AtomicU64, u64, Option<core::time::Duration>) -> bool>(
+        &self,
+        timeout: Option<core::time::Duration>,
+        wait: W,
+    ) -> bool {
+        if self.is_ready() {
+            return true;
+        }
+        let deadline = timeout.map(|timeout| std::time::Instant::now() + timeout);
+        let hdr = self.queue.hdr();
+        if self.dir == WaitDirection::Submit {
+            hdr.inc_submit_waiting();
+        }
+
+        let ready = loop {
+            let (pt, val) = match self.dir {
+                WaitDirection::Receive => self.queue.setup_sleep_simple(),
+                WaitDirection::Submit => self.queue.setup_send_sleep_simple(),
+            };
+
+            // Re-check after announcing that we are going to sleep, since the other side may
+            // have already made progress without seeing us as waiting.
+            if self.is_ready() {
+                break true;
+            }
+
+            let remaining = match deadline {
+                Some(deadline) => {
+                    match deadline.checked_duration_since(std::time::Instant::now()) {
+                        Some(remaining) if !remaining.is_zero() => Some(remaining),
+                        _ => break false,
+                    }
+                }
+                None => None,
+            };
+
+            if val != u64::MAX && !wait(pt, val, remaining) {
+                break self.is_ready();
+            }
+        };
+
+        match self.dir {
+            WaitDirection::Receive => hdr.consumer_set_waiting(false),
+            WaitDirection::Submit => hdr.dec_submit_waiting(),
+        }
+        ready
+    }
+}
+
 #[cfg(any(feature = "std", test))]
 /// Wait for receiving on multiple raw queues. If any of the passed raw queues can return data, they
 /// will do so by writing it into the output array at the same index that they are in the `queues`
@@ -607,92 +957,657 @@ pub fn multi_receive<T: Copy, W: Fn(&[(Option<&AtomicU64>, u64)]), R: Fn(&[Optio
     }
 }
 
-#[cfg(test)]
-mod tests {
-    #![allow(soft_unstable)]
-    use std::sync::atomic::{AtomicU64, Ordering};
-
-    //   use syscalls::SyscallArgs;
-    use crate::multi_receive;
-    use crate::{QueueEntry, QueueError, RawQueue, RawQueueHdr, ReceiveFlags, SubmissionFlags};
+#[cfg(any(feature = "std", test))]
+/// Like [multi_receive], but drains every entry that is currently ready in each queue, instead of
+/// taking at most one. Entries from `queues[i]` are appended to `output[i]`, in queue order, and
+/// the total number of entries received across all queues is returned. The function only waits
+/// (via multi_wait) when every queue is empty, and calls multi_ring once before returning data.
+///
+/// To keep a busy submitter from holding the consumer here forever, each queue is drained at most
+/// one queue-length's worth of entries per call.
+///
+/// If flags specifies [ReceiveFlags::NON_BLOCK], then if no data is available, the function returns
+/// immediately with Err([QueueError::WouldBlock]).
+pub fn multi_receive_all<
+    T: Copy,
+    W: Fn(&[(Option<&AtomicU64>, u64)]),
+    R: Fn(&[Option<&AtomicU64>]),
+>(
+    queues: &[&RawQueue<T>],
+    output: &mut [Vec<QueueEntry<T>>],
+    multi_wait: W,
+    multi_ring: R,
+    flags: ReceiveFlags,
+) -> Result<usize, QueueError> {
+    multi_receive_up_to(queues, output, usize::MAX, multi_wait, multi_ring, flags)
+}
 
-    fn wait(x: &AtomicU64, v: u64) {
-        while x.load(Ordering::SeqCst) == v {
-            core::hint::spin_loop();
-        }
+#[cfg(any(feature = "std", test))]
+// Receive at most max entries, taking one entry from each queue in turn so that a limit does not
+// starve later queues. Each queue still gives up at most a queue-length's worth of entries.
+fn multi_receive_up_to<
+    T: Copy,
+    W: Fn(&[(Option<&AtomicU64>, u64)]),
+    R: Fn(&[Option<&AtomicU64>]),
+>(
+    queues: &[&RawQueue<T>],
+    output: &mut [Vec<QueueEntry<T>>],
+    max: usize,
+    multi_wait: W,
+    multi_ring: R,
+    flags: ReceiveFlags,
+) -> Result<usize, QueueError> {
+    if output.len() != queues.len() {
+        return Err(QueueError::Unknown);
     }
-
-    fn wake(_x: &AtomicU64) {
-        //   println!("wake");
+    let mut waiters = Vec::new();
+    waiters.resize(queues.len(), Default::default());
+    let mut ringers = Vec::new();
+    ringers.resize(queues.len(), None);
+    let mut attempts = 100;
+    loop {
+        let mut count = 0;
+        let mut taken = vec![0; queues.len()];
+        let mut done = vec![false; queues.len()];
+        while count < max && done.iter().any(|d| !d) {
+            for (i, q) in queues.iter().enumerate() {
+                if done[i] || count == max {
+                    continue;
+                }
+                let mut item = None;
+                if taken[i] == q.hdr().len()
+                    || q.setup_sleep(attempts == 0, &mut item, &mut waiters[i], &mut ringers[i])
+                        .is_err()
+                {
+                    done[i] = true;
+                    continue;
+                }
+                // Unwrap-Ok: setup_sleep fills in the item on success.
+                output[i].push(item.take().unwrap());
+                taken[i] += 1;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            multi_ring(&ringers);
+            return Ok(count);
+        }
+        if flags.contains(ReceiveFlags::NON_BLOCK) {
+            return Err(QueueError::WouldBlock);
+        }
+        if attempts > 0 {
+            attempts -= 1;
+        } else {
+            multi_wait(&waiters);
+        }
     }
+}
 
-    #[test]
-    fn it_transmits() {
-        let qh = RawQueueHdr::new(4, std::mem::size_of::<QueueEntry<u32>>());
-        let mut buffer = [QueueEntry::<i32>::default(); 1 << 4];
-        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+#[cfg(any(feature = "std", test))]
+/// Applies backpressure to a consumer of several queues by limiting how many received entries may
+/// be in flight (received, but not yet acknowledged with [Dispatcher::ack]) at once. Once the limit
+/// is reached, receiving stops taking entries, so they stay in their queues and submitters block
+/// on full queues as usual. They are woken through multi_ring when the consumer next receives.
+pub struct Dispatcher<'a, T> {
+    queues: &'a [&'a RawQueue<T>],
+    max_in_flight: usize,
+    in_flight: core::sync::atomic::AtomicUsize,
+}
 
-        for i in 0..100 {
-            let res = q.submit(
-                QueueEntry::new(i as u32, i * 10),
-                wait,
-                wake,
-                SubmissionFlags::empty(),
-            );
-            assert_eq!(res, Ok(()));
-            let res = q.receive(wait, wake, ReceiveFlags::empty());
-            assert!(res.is_ok());
-            assert_eq!(res.unwrap().info(), i as u32);
-            assert_eq!(res.unwrap().item(), i * 10);
+#[cfg(any(feature = "std", test))]
+impl<'a, T: Copy> Dispatcher<'a, T> {
+    /// Create a new dispatcher over `queues`, allowing at most `max_in_flight` unacknowledged
+    /// entries.
+    pub fn new(queues: &'a [&'a RawQueue<T>], max_in_flight: usize) -> Self {
+        Self {
+            queues,
+            max_in_flight,
+            in_flight: core::sync::atomic::AtomicUsize::new(0),
         }
     }
 
-    #[test]
-    fn it_fills() {
-        let qh = RawQueueHdr::new(2, std::mem::size_of::<QueueEntry<u32>>());
-        let mut buffer = [QueueEntry::<i32>::default(); 1 << 2];
-        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
-
-        let res = q.submit(QueueEntry::new(1, 7), wait, wake, SubmissionFlags::empty());
-        assert_eq!(res, Ok(()));
-        let res = q.submit(QueueEntry::new(2, 7), wait, wake, SubmissionFlags::empty());
-        assert_eq!(res, Ok(()));
-        let res = q.submit(QueueEntry::new(3, 7), wait, wake, SubmissionFlags::empty());
-        assert_eq!(res, Ok(()));
-        let res = q.submit(QueueEntry::new(4, 7), wait, wake, SubmissionFlags::empty());
-        assert_eq!(res, Ok(()));
-        let res = q.submit(
-            QueueEntry::new(1, 7),
-            wait,
-            wake,
-            SubmissionFlags::NON_BLOCK,
-        );
-        assert_eq!(res, Err(QueueError::WouldBlock));
+    /// Get the number of entries that have been received but not yet acknowledged.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
     }
 
-    #[test]
-    fn it_nonblock_receives() {
-        let qh = RawQueueHdr::new(4, std::mem::size_of::<QueueEntry<u32>>());
-        let mut buffer = [QueueEntry::<i32>::default(); 1 << 4];
-        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+    /// Like [multi_receive_all], but receives no more entries than would bring the number in
+    /// flight up to the limit. If the limit has already been reached, returns
+    /// Err([QueueError::WouldBlock]) immediately, regardless of flags, since waiting for entries
+    /// could not make room.
+    pub fn receive<W: Fn(&[(Option<&AtomicU64>, u64)]), R: Fn(&[Option<&AtomicU64>])>(
+        &self,
+        output: &mut [Vec<QueueEntry<T>>],
+        multi_wait: W,
+        multi_ring: R,
+        flags: ReceiveFlags,
+    ) -> Result<usize, QueueError> {
+        // Reserve all the remaining room, so that concurrent receivers cannot overshoot the
+        // limit, and give back whatever is not used.
+        let prev = self
+            .in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |cur| {
+                (cur < self.max_in_flight).then_some(self.max_in_flight)
+            })
+            .map_err(|_| QueueError::WouldBlock)?;
+        let room = self.max_in_flight - prev;
+        let res = multi_receive_up_to(self.queues, output, room, multi_wait, multi_ring, flags);
+        let count = *res.as_ref().unwrap_or(&0);
+        self.in_flight.fetch_sub(room - count, Ordering::SeqCst);
+        res
+    }
 
-        let res = q.submit(QueueEntry::new(1, 7), wait, wake, SubmissionFlags::empty());
-        assert_eq!(res, Ok(()));
-        let res = q.receive(wait, wake, ReceiveFlags::empty());
-        assert!(res.is_ok());
-        assert_eq!(res.unwrap().info(), 1);
-        assert_eq!(res.unwrap().item(), 7);
-        let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK);
-        assert_eq!(res.unwrap_err(), QueueError::WouldBlock);
+    /// Acknowledge that `count` received entries have been handled, making room for more.
+    pub fn ack(&self, count: usize) {
+        let prev = self.in_flight.fetch_sub(count, Ordering::SeqCst);
+        debug_assert!(
+            prev >= count,
+            "acknowledged more entries than were received"
+        );
     }
+}
 
-    #[test]
-    fn it_multi_receives() {
-        let qh1 = RawQueueHdr::new(4, std::mem::size_of::<QueueEntry<u32>>());
-        let mut buffer1 = [QueueEntry::<i32>::default(); 1 << 4];
-        let q1 = unsafe { RawQueue::new(&qh1, buffer1.as_mut_ptr()) };
+#[cfg(any(feature = "std", test))]
+/// Correlates submissions with their completions using the `info` tag of [QueueEntry].
+///
+/// A sender calls [CompletionMap::register] before submitting a request, and submits it with the
+/// returned [Pending::info] tag. Whoever receives completions passes each one to
+/// [CompletionMap::complete_entry], which hands the item to the [Pending] with the matching tag and
+/// wakes it, regardless of the order in which completions arrive. Tags are handed out in increasing
+/// order (wrapping), skipping any that are still outstanding.
+pub struct CompletionMap<T> {
+    next: AtomicU32,
+    pending: std::sync::Mutex<std::collections::BTreeMap<u32, std::sync::Arc<CompletionSlot<T>>>>,
+}
+
+#[cfg(any(feature = "std", test))]
+struct CompletionSlot<T> {
+    state: std::sync::Mutex<CompletionState<T>>,
+    cv: std::sync::Condvar,
+}
+
+#[cfg(any(feature = "std", test))]
+struct CompletionState<T> {
+    item: Option<T>,
+    waker: Option<core::task::Waker>,
+}
+
+#[cfg(any(feature = "std", test))]
+impl<T> Default for CompletionMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+impl<T> CompletionMap<T> {
+    /// Construct a new, empty CompletionMap.
+    pub fn new() -> Self {
+        Self {
+            next: AtomicU32::new(0),
+            pending: std::sync::Mutex::new(std::collections::BTreeMap::new()),
+        }
+    }
+
+    /// Allocate an info tag for a new submission, and register a waiter for its completion.
+    pub fn register(&self) -> Pending<'_, T> {
+        let slot = std::sync::Arc::new(CompletionSlot {
+            state: std::sync::Mutex::new(CompletionState {
+                item: None,
+                waker: None,
+            }),
+            cv: std::sync::Condvar::new(),
+        });
+        let mut pending = self.pending.lock().unwrap();
+        let info = loop {
+            let info = self.next.fetch_add(1, Ordering::Relaxed);
+            if !pending.contains_key(&info) {
+                break info;
+            }
+        };
+        pending.insert(info, slot.clone());
+        Pending {
+            map: self,
+            info,
+            slot,
+        }
+    }
+
+    /// Resolve the submission tagged `info` with `item`, waking its waiter. If no submission with
+    /// that tag is outstanding, the item is handed back.
+    pub fn complete(&self, info: u32, item: T) -> Result<(), T> {
+        let Some(slot) = self.pending.lock().unwrap().remove(&info) else {
+            return Err(item);
+        };
+        let mut state = slot.state.lock().unwrap();
+        state.item = Some(item);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        slot.cv.notify_all();
+        Ok(())
+    }
+
+    /// Resolve a completion received from a queue, using its info tag. See
+    /// [CompletionMap::complete].
+    pub fn complete_entry(&self, entry: QueueEntry<T>) -> Result<(), QueueEntry<T>> {
+        let info = entry.info();
+        self.complete(info, entry.item())
+            .map_err(|item| QueueEntry::new(info, item))
+    }
+
+    /// The number of submissions still waiting for a completion.
+    pub fn outstanding(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+/// A submission registered with a [CompletionMap], waiting for its completion. Dropping it before
+/// the completion arrives unregisters the tag, and a late completion for it is handed back by
+/// [CompletionMap::complete].
+pub struct Pending<'a, T> {
+    map: &'a CompletionMap<T>,
+    info: u32,
+    slot: std::sync::Arc<CompletionSlot<T>>,
+}
+
+#[cfg(any(feature = "std", test))]
+impl<'a, T> Pending<'a, T> {
+    /// The info tag to submit the request with.
+    pub fn info(&self) -> u32 {
+        self.info
+    }
+
+    /// Take the completion, if it has arrived.
+    pub fn try_take(&self) -> Option<T> {
+        self.slot.state.lock().unwrap().item.take()
+    }
+
+    /// Take the completion if it has arrived, otherwise register the context's waker to be woken
+    /// when it does.
+    pub fn poll_take(&self, cx: &mut core::task::Context<'_>) -> core::task::Poll<T> {
+        let mut state = self.slot.state.lock().unwrap();
+        match state.item.take() {
+            Some(item) => core::task::Poll::Ready(item),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                core::task::Poll::Pending
+            }
+        }
+    }
+
+    /// Block until the completion arrives, and take it.
+    pub fn wait(self) -> T {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.item.take() {
+                return item;
+            }
+            state = self.slot.cv.wait(state).unwrap();
+        }
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+impl<'a, T> Drop for Pending<'a, T> {
+    fn drop(&mut self) {
+        let mut pending = self.map.pending.lock().unwrap();
+        if pending
+            .get(&self.info)
+            .is_some_and(|slot| std::sync::Arc::ptr_eq(slot, &self.slot))
+        {
+            pending.remove(&self.info);
+        }
+    }
+}
+
+#[cfg(any(feature = "std", test))]
+/// A matched pair of queues for running requests and responses: requests of type S are submitted
+/// on one queue, and responses of type C come back on the other, correlated with their request by
+/// the `info` tag of [QueueEntry]. The other end must receive from [RawQueuePair::submission] and
+/// answer each request on [RawQueuePair::completion] using the same info tag.
+///
+/// Any number of threads may submit and wait for completions. Only one thread receives from the
+/// completion queue at a time, and completions it receives for other tags are held until their
+/// waiters collect them.
+pub struct RawQueuePair<S, C> {
+    submission: RawQueue<S>,
+    completion: RawQueue<C>,
+    next: AtomicU32,
+    state: std::sync::Mutex<PairState<C>>,
+    // Signalled when a completion is stashed, or when the receiving thread stops receiving.
+    changed: std::sync::Condvar,
+}
+
+#[cfg(any(feature = "std", test))]
+struct PairState<C> {
+    // Completions that arrived for tags other than the receiving thread's.
+    completed: std::collections::BTreeMap<u32, C>,
+    // Whether a thread is receiving from the completion queue. That thread is its single
+    // consumer, and receives without holding the lock.
+    receiving: bool,
+}
+
+#[cfg(any(feature = "std", test))]
+impl<S: Copy, C: Copy> RawQueuePair<S, C> {
+    /// Construct a new queue pair out of a submission queue and a completion queue.
+    pub fn new(submission: RawQueue<S>, completion: RawQueue<C>) -> Self {
+        Self {
+            submission,
+            completion,
+            next: AtomicU32::new(0),
+            state: std::sync::Mutex::new(PairState {
+                completed: std::collections::BTreeMap::new(),
+                receiving: false,
+            }),
+            changed: std::sync::Condvar::new(),
+        }
+    }
+
+    /// Get the submission queue, for the end that receives requests.
+    pub fn submission(&self) -> &RawQueue<S> {
+        &self.submission
+    }
+
+    /// Get the completion queue, for the end that sends responses.
+    pub fn completion(&self) -> &RawQueue<C> {
+        &self.completion
+    }
+
+    /// Submit a request, returning the info tag to wait for its completion with. The wait and ring
+    /// callbacks and flags work like [RawQueue::submit]. Tags are handed out in increasing order
+    /// (wrapping).
+    pub fn submit<W: Fn(&AtomicU64, u64), R: Fn(&AtomicU64)>(
+        &self,
+        req: S,
+        wait: W,
+        ring: R,
+        flags: SubmissionFlags,
+    ) -> Result<u32, QueueError> {
+        let info = self.next.fetch_add(1, Ordering::Relaxed);
+        self.submission
+            .submit(QueueEntry::new(info, req), wait, ring, flags)?;
+        Ok(info)
+    }
+
+    /// Wait for the completion of the request tagged `info`, and take it. The wait and ring
+    /// callbacks work like [RawQueue::receive]. If [ReceiveFlags::NON_BLOCK] is set and the
+    /// completion has not arrived, returns Err([QueueError::WouldBlock]).
+    ///
+    /// Each tag must be waited for at most once, and only after it was returned by
+    /// [RawQueuePair::submit], otherwise this may block forever.
+    pub fn wait_completion<W: Fn(&AtomicU64, u64), R: Fn(&AtomicU64)>(
+        &self,
+        info: u32,
+        wait: W,
+        ring: R,
+        flags: ReceiveFlags,
+    ) -> Result<C, QueueError> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.completed.remove(&info) {
+                return Ok(item);
+            }
+            if state.receiving {
+                // Another thread is receiving, and will stash our completion if it sees it.
+                if flags.contains(ReceiveFlags::NON_BLOCK) {
+                    return Err(QueueError::WouldBlock);
+                }
+                state = self.changed.wait(state).unwrap();
+                continue;
+            }
+
+            // Become the receiver, and receive without the lock so that other waiters can collect
+            // what we stash.
+            state.receiving = true;
+            drop(state);
+            let receiver = Receiver { pair: self };
+            let res = self.completion.receive(&wait, &ring, flags);
+            drop(receiver);
+            state = self.state.lock().unwrap();
+            let entry = res?;
+            if entry.info() == info {
+                return Ok(entry.item());
+            }
+            state.completed.insert(entry.info(), entry.item());
+        }
+    }
+}
+
+// Held by the thread receiving for a RawQueuePair. Dropping it gives up receiving, even if the
+// receive panicked, so that the other waiters do not wait forever for a receiver that is gone.
+#[cfg(any(feature = "std", test))]
+struct Receiver<'a, S, C> {
+    pair: &'a RawQueuePair<S, C>,
+}
+
+#[cfg(any(feature = "std", test))]
+impl<S, C> Drop for Receiver<'_, S, C> {
+    fn drop(&mut self) {
+        let mut state = self
+            .pair
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.receiving = false;
+        // Wake everyone: the waiter for a stashed entry collects it, and someone else takes over
+        // receiving.
+        self.pair.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(soft_unstable)]
+    use std::{
+        sync::atomic::{AtomicU64, Ordering},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        entry_stride, CompletionMap, QueueEntry, QueueError, QueueWaiter, RawQueue, RawQueueHdr,
+        RawQueuePair, ReceiveFlags, SubmissionFlags,
+    };
+    //   use syscalls::SyscallArgs;
+    use crate::{multi_receive, multi_receive_all, Dispatcher};
+
+    fn wait(x: &AtomicU64, v: u64) {
+        while x.load(Ordering::SeqCst) == v {
+            core::hint::spin_loop();
+        }
+    }
+
+    fn wait_timeout(x: &AtomicU64, v: u64, timeout: Option<Duration>) -> bool {
+        let start = Instant::now();
+        while x.load(Ordering::SeqCst) == v {
+            if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+                return false;
+            }
+            core::hint::spin_loop();
+        }
+        true
+    }
 
-        let qh2 = RawQueueHdr::new(4, std::mem::size_of::<QueueEntry<u32>>());
+    fn wake(_x: &AtomicU64) {
+        //   println!("wake");
+    }
+
+    #[test]
+    fn it_times_out_submit_without_wedging() {
+        let qh = RawQueueHdr::new_with_spin(1, entry_stride::<i32>(), 0);
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 1];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+        let wait_deadline =
+            |x: &AtomicU64, v: u64, remaining: Duration| wait_timeout(x, v, Some(remaining));
+
+        for i in 0..2 {
+            let res = q.submit_deadline(
+                QueueEntry::new(i, i as i32),
+                wait_deadline,
+                wake,
+                SubmissionFlags::empty(),
+                Duration::from_millis(10),
+            );
+            assert_eq!(res, Ok(()));
+        }
+
+        let start = Instant::now();
+        let res = q.submit_deadline(
+            QueueEntry::new(2, 2),
+            wait_deadline,
+            wake,
+            SubmissionFlags::empty(),
+            Duration::from_millis(10),
+        );
+        assert_eq!(res, Err(QueueError::TimedOut));
+        assert!(start.elapsed() >= Duration::from_millis(10));
+
+        // The timed out submit must not have left a hole, so the consumer sees every other entry
+        // in order, and the queue keeps working afterwards.
+        for i in 0..2 {
+            let res = q.receive(wait, wake, ReceiveFlags::empty()).unwrap();
+            assert_eq!(res.info(), i);
+        }
+        for i in 3..10 {
+            let res = q.submit_deadline(
+                QueueEntry::new(i, i as i32),
+                wait_deadline,
+                wake,
+                SubmissionFlags::empty(),
+                Duration::from_millis(10),
+            );
+            assert_eq!(res, Ok(()));
+            let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK).unwrap();
+            assert_eq!(res.info(), i);
+            assert_eq!(res.item(), i as i32);
+        }
+    }
+
+    #[test]
+    fn it_transmits() {
+        let qh = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 4];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        for i in 0..100 {
+            let res = q.submit(
+                QueueEntry::new(i as u32, i * 10),
+                wait,
+                wake,
+                SubmissionFlags::empty(),
+            );
+            assert_eq!(res, Ok(()));
+            let res = q.receive(wait, wake, ReceiveFlags::empty());
+            assert!(res.is_ok());
+            assert_eq!(res.unwrap().info(), i as u32);
+            assert_eq!(res.unwrap().item(), i * 10);
+        }
+    }
+
+    #[test]
+    fn it_fills() {
+        let qh = RawQueueHdr::new(2, entry_stride::<i32>());
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 2];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        let res = q.submit(QueueEntry::new(1, 7), wait, wake, SubmissionFlags::empty());
+        assert_eq!(res, Ok(()));
+        let res = q.submit(QueueEntry::new(2, 7), wait, wake, SubmissionFlags::empty());
+        assert_eq!(res, Ok(()));
+        let res = q.submit(QueueEntry::new(3, 7), wait, wake, SubmissionFlags::empty());
+        assert_eq!(res, Ok(()));
+        let res = q.submit(QueueEntry::new(4, 7), wait, wake, SubmissionFlags::empty());
+        assert_eq!(res, Ok(()));
+        let res = q.submit(
+            QueueEntry::new(1, 7),
+            wait,
+            wake,
+            SubmissionFlags::NON_BLOCK,
+        );
+        assert_eq!(res, Err(QueueError::WouldBlock));
+    }
+
+    #[test]
+    fn it_nonblock_receives() {
+        let qh = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 4];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        let res = q.submit(QueueEntry::new(1, 7), wait, wake, SubmissionFlags::empty());
+        assert_eq!(res, Ok(()));
+        let res = q.receive(wait, wake, ReceiveFlags::empty());
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap().info(), 1);
+        assert_eq!(res.unwrap().item(), 7);
+        let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK);
+        assert_eq!(res.unwrap_err(), QueueError::WouldBlock);
+    }
+
+    #[test]
+    fn it_receives_by_ref() {
+        #[derive(Clone, Copy)]
+        struct Large([u64; 64]);
+        impl Default for Large {
+            fn default() -> Self {
+                Self([0; 64])
+            }
+        }
+
+        let qh = RawQueueHdr::new(2, entry_stride::<Large>());
+        let mut buffer = [QueueEntry::<Large>::default(); 1 << 2];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        let mut item = Large::default();
+        item.0[63] = 42;
+        let res = q.submit(
+            QueueEntry::new(3, item),
+            wait,
+            wake,
+            SubmissionFlags::empty(),
+        );
+        assert_eq!(res, Ok(()));
+
+        let slot = &buffer[0] as *const QueueEntry<Large>;
+        let res = q.receive_ref(wait, wake, ReceiveFlags::empty(), |entry| {
+            assert!(core::ptr::eq(entry, slot));
+            (entry.info(), entry.item_ref().0[63])
+        });
+        assert_eq!(res, Ok((3, 42)));
+
+        let res = q.receive_ref(wait, wake, ReceiveFlags::NON_BLOCK, |_| ());
+        assert_eq!(res, Err(QueueError::WouldBlock));
+    }
+
+    #[test]
+    fn it_skips_spinning() {
+        let qh = RawQueueHdr::new_with_spin(2, entry_stride::<i32>(), 0);
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 2];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        let waits = std::cell::Cell::new(0);
+        let res = q.receive(
+            |_, _| {
+                // With no spinning, the consumer must be marked as waiting before it sleeps.
+                assert!(qh.consumer_waiting());
+                waits.set(waits.get() + 1);
+                q.submit(QueueEntry::new(1, 7), wait, wake, SubmissionFlags::empty())
+                    .unwrap();
+            },
+            wake,
+            ReceiveFlags::empty(),
+        );
+        assert_eq!(res.unwrap().item(), 7);
+        assert_eq!(waits.get(), 1);
+    }
+
+    #[test]
+    fn it_multi_receives() {
+        let qh1 = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer1 = [QueueEntry::<i32>::default(); 1 << 4];
+        let q1 = unsafe { RawQueue::new(&qh1, buffer1.as_mut_ptr()) };
+
+        let qh2 = RawQueueHdr::new(4, entry_stride::<i32>());
         let mut buffer2 = [QueueEntry::<i32>::default(); 1 << 4];
         let q2 = unsafe { RawQueue::new(&qh2, buffer2.as_mut_ptr()) };
 
@@ -716,6 +1631,476 @@ mod tests {
         assert_eq!(output[1].unwrap().item(), 8);
     }
 
+    #[test]
+    fn it_multi_receives_all() {
+        let qh1 = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer1 = [QueueEntry::<i32>::default(); 1 << 4];
+        let q1 = unsafe { RawQueue::new(&qh1, buffer1.as_mut_ptr()) };
+
+        let qh2 = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer2 = [QueueEntry::<i32>::default(); 1 << 4];
+        let q2 = unsafe { RawQueue::new(&qh2, buffer2.as_mut_ptr()) };
+
+        for i in 0..3 {
+            let res = q1.submit(
+                QueueEntry::new(i, 10 + i as i32),
+                wait,
+                wake,
+                SubmissionFlags::empty(),
+            );
+            assert_eq!(res, Ok(()));
+            let res = q2.submit(
+                QueueEntry::new(i, 20 + i as i32),
+                wait,
+                wake,
+                SubmissionFlags::empty(),
+            );
+            assert_eq!(res, Ok(()));
+        }
+
+        let mut output = [Vec::new(), Vec::new()];
+        let res = multi_receive_all(
+            &[&q1, &q2],
+            &mut output,
+            |_| {},
+            |_| {},
+            ReceiveFlags::empty(),
+        );
+        assert_eq!(res, Ok(6));
+        for (q, entries) in output.iter().enumerate() {
+            let items: Vec<_> = entries.iter().map(|e| (e.info(), e.item())).collect();
+            let base = 10 * (q as i32 + 1);
+            assert_eq!(items, [(0, base), (1, base + 1), (2, base + 2)]);
+        }
+
+        let res = multi_receive_all(
+            &[&q1, &q2],
+            &mut output,
+            |_| {},
+            |_| {},
+            ReceiveFlags::NON_BLOCK,
+        );
+        assert_eq!(res, Err(QueueError::WouldBlock));
+        assert_eq!(output[0].len(), 3);
+    }
+
+    #[test]
+    fn it_limits_in_flight() {
+        let qh1 = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer1 = [QueueEntry::<i32>::default(); 1 << 4];
+        let q1 = unsafe { RawQueue::new(&qh1, buffer1.as_mut_ptr()) };
+
+        let qh2 = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer2 = [QueueEntry::<i32>::default(); 1 << 4];
+        let q2 = unsafe { RawQueue::new(&qh2, buffer2.as_mut_ptr()) };
+
+        for i in 0..4 {
+            for q in [&q1, &q2] {
+                let res = q.submit(
+                    QueueEntry::new(i, i as i32),
+                    wait,
+                    wake,
+                    SubmissionFlags::empty(),
+                );
+                assert_eq!(res, Ok(()));
+            }
+        }
+
+        let queues = [&q1, &q2];
+        let dispatcher = Dispatcher::new(&queues, 3);
+        let mut output = [Vec::new(), Vec::new()];
+        let res = dispatcher.receive(&mut output, |_| {}, |_| {}, ReceiveFlags::empty());
+        assert_eq!(res, Ok(3));
+        assert_eq!(dispatcher.in_flight(), 3);
+        // Both queues are served, even though the first one could fill the limit on its own.
+        assert_eq!(output[0].len(), 2);
+        assert_eq!(output[1].len(), 1);
+
+        let res = dispatcher.receive(&mut output, |_| {}, |_| {}, ReceiveFlags::empty());
+        assert_eq!(res, Err(QueueError::WouldBlock));
+
+        dispatcher.ack(2);
+        let res = dispatcher.receive(&mut output, |_| {}, |_| {}, ReceiveFlags::empty());
+        assert_eq!(res, Ok(2));
+        assert_eq!(dispatcher.in_flight(), 3);
+
+        dispatcher.ack(3);
+        let res = dispatcher.receive(&mut output, |_| {}, |_| {}, ReceiveFlags::empty());
+        assert_eq!(res, Ok(3));
+        for (q, entries) in output.iter().enumerate() {
+            let infos: Vec<_> = entries.iter().map(|e| e.info()).collect();
+            assert_eq!(infos, [0, 1, 2, 3], "queue {q}");
+        }
+
+        dispatcher.ack(3);
+        let res = dispatcher.receive(&mut output, |_| {}, |_| {}, ReceiveFlags::NON_BLOCK);
+        assert_eq!(res, Err(QueueError::WouldBlock));
+        assert_eq!(dispatcher.in_flight(), 0);
+    }
+
+    #[test]
+    fn it_wraps_counters() {
+        const LEN: usize = 4;
+        let qh = RawQueueHdr::new(2, entry_stride::<i32>());
+        let mut buffer = [QueueEntry::<i32>::default(); LEN];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        // Start two laps before the counters wrap, so that the zeroed buffer is not mistaken for
+        // submitted entries.
+        let start = (1u32 << 31) - 2 * LEN as u32;
+        qh.head.store(start, Ordering::SeqCst);
+        qh.tail.store(start as u64, Ordering::SeqCst);
+        qh.bell.store(start as u64, Ordering::SeqCst);
+
+        let mut next = 0;
+        for _ in 0..5 {
+            for i in 0..LEN {
+                let res = q.submit(
+                    QueueEntry::new((next + i) as u32, (next + i) as i32),
+                    wait,
+                    wake,
+                    SubmissionFlags::NON_BLOCK,
+                );
+                assert_eq!(res, Ok(()));
+            }
+            assert!(!qh.submit_ready());
+            for _ in 0..LEN {
+                let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK).unwrap();
+                assert_eq!(res.item(), next as i32);
+                next += 1;
+            }
+            let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK);
+            assert_eq!(res.err(), Some(QueueError::WouldBlock));
+        }
+        assert!(qh.tail.load(Ordering::SeqCst) < start as u64);
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_rejects_oversized_queues() {
+        RawQueueHdr::new(crate::MAX_L2LEN + 1, entry_stride::<u32>());
+    }
+
+    #[test]
+    fn it_checks_stride() {
+        let mut buffer = [QueueEntry::<u64>::default(); 1 << 4];
+
+        let qh = RawQueueHdr::new(4, entry_stride::<u32>());
+        let res = unsafe { RawQueue::new_checked(&qh, buffer.as_mut_ptr()) };
+        assert_eq!(res.err(), Some(QueueError::StrideMismatch));
+
+        let qh = RawQueueHdr::new(4, entry_stride::<u64>());
+        let res = unsafe { RawQueue::new_checked(&qh, buffer.as_mut_ptr()) };
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn it_drains() {
+        let qh = RawQueueHdr::new(2, entry_stride::<i32>());
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 2];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        assert_eq!(q.drain(wake), 0);
+        for round in 0..3 {
+            for i in 0..3 {
+                let res = q.submit(
+                    QueueEntry::new(i, round),
+                    wait,
+                    wake,
+                    SubmissionFlags::empty(),
+                );
+                assert_eq!(res, Ok(()));
+            }
+            assert_eq!(q.drain(wake), 3);
+            let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK);
+            assert_eq!(res.unwrap_err(), QueueError::WouldBlock);
+        }
+
+        let res = q.submit(QueueEntry::new(9, 9), wait, wake, SubmissionFlags::empty());
+        assert_eq!(res, Ok(()));
+        let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK);
+        assert_eq!(res.unwrap().info(), 9);
+    }
+
+    #[test]
+    fn it_waits_for_receive() {
+        let qh = RawQueueHdr::new(4, entry_stride::<i32>());
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 4];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        let waiter = QueueWaiter::for_receive(&q);
+        assert!(!waiter.wait(Some(Duration::from_millis(10)), wait_timeout));
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(10));
+                let res = q.submit(QueueEntry::new(1, 7), wait, wake, SubmissionFlags::empty());
+                assert_eq!(res, Ok(()));
+            });
+            assert!(waiter.wait(None, wait_timeout));
+        });
+
+        let res = q.receive(wait, wake, ReceiveFlags::NON_BLOCK);
+        assert_eq!(res.unwrap().item(), 7);
+    }
+
+    #[test]
+    fn it_waits_for_submit() {
+        let qh = RawQueueHdr::new(2, entry_stride::<i32>());
+        let mut buffer = [QueueEntry::<i32>::default(); 1 << 2];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        for i in 0..4 {
+            let res = q.submit(QueueEntry::new(i, 7), wait, wake, SubmissionFlags::empty());
+            assert_eq!(res, Ok(()));
+        }
+
+        let waiter = QueueWaiter::for_submit(&q);
+        assert!(!waiter.wait(Some(Duration::from_millis(10)), wait_timeout));
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(10));
+                let res = q.receive(wait, wake, ReceiveFlags::empty());
+                assert_eq!(res.unwrap().info(), 0);
+            });
+            assert!(waiter.wait(None, wait_timeout));
+        });
+
+        let res = q.submit(
+            QueueEntry::new(4, 7),
+            wait,
+            wake,
+            SubmissionFlags::NON_BLOCK,
+        );
+        assert_eq!(res, Ok(()));
+    }
+
+    #[test]
+    fn it_orders_contended_submits() {
+        const PRODUCERS: u32 = 4;
+        const PER_PRODUCER: u32 = 100;
+        let qh = RawQueueHdr::new_with_spin(2, entry_stride::<u32>(), 10);
+        let mut buffer = [QueueEntry::<u32>::default(); 1 << 2];
+        let q = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+
+        std::thread::scope(|s| {
+            for p in 0..PRODUCERS {
+                let q = &q;
+                s.spawn(move || {
+                    for i in 0..PER_PRODUCER {
+                        let res =
+                            q.submit(QueueEntry::new(p, i), wait, wake, SubmissionFlags::empty());
+                        assert_eq!(res, Ok(()));
+                    }
+                });
+            }
+
+            // Every submitter is served eventually, and each one's entries arrive in the order
+            // it reserved their slots.
+            let mut next = [0u32; PRODUCERS as usize];
+            for _ in 0..(PRODUCERS * PER_PRODUCER) {
+                let entry = q.receive(wait, wake, ReceiveFlags::empty()).unwrap();
+                let p = entry.info() as usize;
+                assert_eq!(entry.item(), next[p]);
+                next[p] += 1;
+            }
+            assert!(next.iter().all(|n| *n == PER_PRODUCER));
+        });
+    }
+
+    #[test]
+    fn it_matches_completions_out_of_order() {
+        let qh = RawQueueHdr::new(4, entry_stride::<u32>());
+        let mut buffer = [QueueEntry::<u32>::default(); 1 << 4];
+        let cq = unsafe { RawQueue::new(&qh, buffer.as_mut_ptr()) };
+        let map = CompletionMap::<u32>::new();
+
+        let pending: Vec<_> = (0..4).map(|_| map.register()).collect();
+        let infos: Vec<_> = pending.iter().map(|p| p.info()).collect();
+        assert_eq!(infos, [0, 1, 2, 3]);
+        assert_eq!(map.outstanding(), 4);
+
+        std::thread::scope(|s| {
+            let waiters: Vec<_> = pending
+                .into_iter()
+                .map(|p| s.spawn(move || (p.info(), p.wait())))
+                .collect();
+
+            // The receiver answers in reverse order, with a value derived from the tag.
+            for info in infos.iter().rev() {
+                let res = cq.submit(
+                    QueueEntry::new(*info, *info * 10),
+                    wait,
+                    wake,
+                    SubmissionFlags::empty(),
+                );
+                assert_eq!(res, Ok(()));
+            }
+            for _ in 0..infos.len() {
+                let entry = cq.receive(wait, wake, ReceiveFlags::empty()).unwrap();
+                assert!(map.complete_entry(entry).is_ok());
+            }
+
+            for waiter in waiters {
+                let (info, item) = waiter.join().unwrap();
+                assert_eq!(item, info * 10);
+            }
+        });
+        assert_eq!(map.outstanding(), 0);
+
+        // Completions for unknown or abandoned tags are handed back.
+        assert_eq!(map.complete(0, 1), Err(1));
+        let abandoned = map.register();
+        let info = abandoned.info();
+        assert_eq!(info, 4);
+        drop(abandoned);
+        assert_eq!(map.complete(info, 2), Err(2));
+    }
+
+    #[test]
+    fn it_round_trips_pair() {
+        let sqh = RawQueueHdr::new(2, entry_stride::<u32>());
+        let mut sbuffer = [QueueEntry::<u32>::default(); 1 << 2];
+        let cqh = RawQueueHdr::new(2, entry_stride::<u64>());
+        let mut cbuffer = [QueueEntry::<u64>::default(); 1 << 2];
+        let pair = unsafe {
+            RawQueuePair::new(
+                RawQueue::new(&sqh, sbuffer.as_mut_ptr()),
+                RawQueue::new(&cqh, cbuffer.as_mut_ptr()),
+            )
+        };
+
+        let first = pair
+            .submit(3, wait, wake, SubmissionFlags::empty())
+            .unwrap();
+        let second = pair
+            .submit(4, wait, wake, SubmissionFlags::empty())
+            .unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            pair.wait_completion(first, wait, wake, ReceiveFlags::NON_BLOCK),
+            Err(QueueError::WouldBlock)
+        );
+
+        // The other end answers in reverse order, echoing the tag of each request.
+        let requests: Vec<_> = (0..2)
+            .map(|_| {
+                pair.submission()
+                    .receive(wait, wake, ReceiveFlags::empty())
+                    .unwrap()
+            })
+            .collect();
+        for req in requests.iter().rev() {
+            let res = pair.completion().submit(
+                QueueEntry::new(req.info(), req.item() as u64 * 100),
+                wait,
+                wake,
+                SubmissionFlags::empty(),
+            );
+            assert_eq!(res, Ok(()));
+        }
+
+        assert_eq!(
+            pair.wait_completion(first, wait, wake, ReceiveFlags::empty()),
+            Ok(300)
+        );
+        assert_eq!(
+            pair.wait_completion(second, wait, wake, ReceiveFlags::NON_BLOCK),
+            Ok(400)
+        );
+    }
+
+    #[test]
+    fn it_hands_off_pair_completions() {
+        let sqh = RawQueueHdr::new(2, entry_stride::<u32>());
+        let mut sbuffer = [QueueEntry::<u32>::default(); 1 << 2];
+        let cqh = RawQueueHdr::new(2, entry_stride::<u64>());
+        let mut cbuffer = [QueueEntry::<u64>::default(); 1 << 2];
+        let pair = unsafe {
+            RawQueuePair::new(
+                RawQueue::new(&sqh, sbuffer.as_mut_ptr()),
+                RawQueue::new(&cqh, cbuffer.as_mut_ptr()),
+            )
+        };
+
+        let first = pair
+            .submit(3, wait, wake, SubmissionFlags::empty())
+            .unwrap();
+        let second = pair
+            .submit(4, wait, wake, SubmissionFlags::empty())
+            .unwrap();
+        std::thread::scope(|s| {
+            // This thread becomes the receiver, and stays blocked until the first request is
+            // answered.
+            let waiter = s.spawn(|| pair.wait_completion(first, wait, wake, ReceiveFlags::empty()));
+            std::thread::sleep(Duration::from_millis(10));
+
+            // The second completion must reach its waiter while the receiver is still blocked.
+            let res = pair.completion().submit(
+                QueueEntry::new(second, 400),
+                wait,
+                wake,
+                SubmissionFlags::empty(),
+            );
+            assert_eq!(res, Ok(()));
+            assert_eq!(
+                pair.wait_completion(second, wait, wake, ReceiveFlags::empty()),
+                Ok(400)
+            );
+            assert!(!waiter.is_finished());
+
+            let res = pair.completion().submit(
+                QueueEntry::new(first, 300),
+                wait,
+                wake,
+                SubmissionFlags::empty(),
+            );
+            assert_eq!(res, Ok(()));
+            assert_eq!(waiter.join().unwrap(), Ok(300));
+        });
+    }
+
+    #[test]
+    fn it_recovers_from_panicking_pair_receiver() {
+        let sqh = RawQueueHdr::new(2, entry_stride::<u32>());
+        let mut sbuffer = [QueueEntry::<u32>::default(); 1 << 2];
+        let cqh = RawQueueHdr::new(2, entry_stride::<u64>());
+        let mut cbuffer = [QueueEntry::<u64>::default(); 1 << 2];
+        let pair = unsafe {
+            RawQueuePair::new(
+                RawQueue::new(&sqh, sbuffer.as_mut_ptr()),
+                RawQueue::new(&cqh, cbuffer.as_mut_ptr()),
+            )
+        };
+
+        let first = pair
+            .submit(3, wait, wake, SubmissionFlags::empty())
+            .unwrap();
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            pair.wait_completion(
+                first,
+                |_, _| panic!("receive failed"),
+                wake,
+                ReceiveFlags::empty(),
+            )
+        }));
+        assert!(res.is_err());
+
+        // The panicking receiver must have given up receiving, or this would wait forever.
+        let res = pair.completion().submit(
+            QueueEntry::new(first, 300),
+            wait,
+            wake,
+            SubmissionFlags::empty(),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(
+            pair.wait_completion(first, wait, wake, ReceiveFlags::empty()),
+            Ok(300)
+        );
+    }
+
     /*
         #[cfg(not(target_os = "twizzler"))]
         extern crate crossbeam;
@@ -724,7 +2109,7 @@ mod tests {
         #[cfg(not(target_os = "twizzler"))]
         #[bench]
         fn two_threads(b: &mut test::Bencher) -> impl Termination {
-            let qh = RawQueueHdr::new(4, std::mem::size_of::<QueueEntry<u32>>());
+            let qh = RawQueueHdr::new(4, entry_stride::<i32>());
             let mut buffer = [QueueEntry::<i32>::default(); 1 << 4];
             let q = unsafe {
                 RawQueue::new(
@@ -8,8 +8,8 @@ use twizzler_abi::{
     },
 };
 use twizzler_object::{CreateError, CreateSpec, Object};
+use twizzler_queue_raw::{entry_stride, QueueEntry, RawQueue, RawQueueHdr};
 pub use twizzler_queue_raw::{QueueBase, QueueError, ReceiveFlags, SubmissionFlags};
-use twizzler_queue_raw::{QueueEntry, RawQueue, RawQueueHdr};
 
 /// A single queue, holding two subqueues (sending and completion). Objects of type S are sent
 /// across the sending queue, and completions of type C are sent back.
@@ -93,9 +93,9 @@ impl<S: Copy, C: Copy> Queue<S, C> {
             let srq: *mut RawQueueHdr = obj.raw_lea_mut(sub_hdr);
             let crq: *mut RawQueueHdr = obj.raw_lea_mut(com_hdr);
             let l2len = sub_queue_len.next_power_of_two().ilog2();
-            srq.write(RawQueueHdr::new(l2len as usize, core::mem::size_of::<S>()));
+            srq.write(RawQueueHdr::new(l2len as usize, entry_stride::<S>()));
             let l2len = com_queue_len.next_power_of_two().ilog2();
-            crq.write(RawQueueHdr::new(l2len as usize, core::mem::size_of::<C>()));
+            crq.write(RawQueueHdr::new(l2len as usize, entry_stride::<C>()));
         })?;
         Ok(obj.into())
     }
@@ -76,6 +76,47 @@ pub trait RawObject {
         Some(unsafe { self.handle().start().add(offset) as *mut u8 })
     }
 
+    /// Write the object back to its backing store, if it was mapped persistent. Volatile objects
+    /// have no backing store, so this does nothing for them.
+    fn sync(&self) {
+        if self.handle().map_flags().contains(MapFlags::PERSIST) {
+            crate::pager::sync_object(self.id());
+        }
+    }
+
+    /// The number of bytes of the object usable for data, starting at the base (that is, excluding
+    /// the null page and the meta page).
+    fn usable_len(&self) -> usize {
+        MAX_SIZE - NULLPAGE_SIZE * 2
+    }
+
+    /// Get a byte slice of the object's data, starting at the base.
+    ///
+    /// # Safety
+    /// The caller must ensure that no one is concurrently mutating this region of the object.
+    ///
+    /// # Panics
+    /// Panics if `len` exceeds [RawObject::usable_len].
+    unsafe fn data_slice(&self, len: usize) -> &[u8] {
+        assert!(len <= self.usable_len());
+        core::slice::from_raw_parts(self.base_ptr::<u8>(), len)
+    }
+
+    /// Get a mutable byte slice of the object's data, starting at the base.
+    ///
+    /// # Safety
+    /// The caller must ensure that no other references to this region of the object exist for the
+    /// lifetime of the returned slice.
+    ///
+    /// # Panics
+    /// Panics if `len` exceeds [RawObject::usable_len], or if the object is not mapped writable.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn data_slice_mut(&self, len: usize) -> &mut [u8] {
+        assert!(self.handle().map_flags().contains(MapFlags::WRITE));
+        assert!(len <= self.usable_len());
+        core::slice::from_raw_parts_mut(self.base_mut_ptr::<u8>(), len)
+    }
+
     /// If the pointer is local to this object, return the offset into the object. Otherwise, return
     /// None.
     fn ptr_local(&self, ptr: *const u8) -> Option<usize> {
@@ -162,3 +203,19 @@ impl<Base: BaseType> TypedObject for Object<Base> {
         unsafe { Ref::from_raw_parts(base, self.handle()) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ObjectBuilder, RawObject};
+
+    #[test]
+    fn data_slices() {
+        let obj = ObjectBuilder::default().build(42u32).unwrap();
+        let bytes = unsafe { obj.data_slice(4) };
+        assert_eq!(bytes, &42u32.to_ne_bytes());
+
+        let bytes = unsafe { obj.data_slice_mut(4) };
+        bytes.copy_from_slice(&7u32.to_ne_bytes());
+        assert_eq!(unsafe { *obj.base_ptr::<u32>() }, 7);
+    }
+}
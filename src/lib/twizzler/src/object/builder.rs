@@ -113,4 +113,38 @@ mod tests {
         let r = unsafe { base_foo.ptr.resolve() };
         assert_eq!(*r, 42);
     }
+
+    #[test]
+    fn create_spec_ties() {
+        use twizzler_abi::syscall::{BackingType, CreateTieFlags, CreateTieSpec, LifetimeType};
+        use twizzler_object::{create_spec, CreateSpec, Object};
+        use twizzler_rt_abi::object::MapFlags;
+
+        use crate::object::RawObject;
+
+        let unmapped = ObjectBuilder::default().build(1u32).unwrap().id();
+        let mapped = Object::<u32>::create_with(
+            &CreateSpec::new(LifetimeType::Volatile, BackingType::Normal),
+            |_| {},
+        )
+        .unwrap();
+
+        let mut spec = CreateSpec::new(LifetimeType::Persistent, BackingType::Normal);
+        spec.lifetime(LifetimeType::Volatile)
+            .tie_to(unmapped, CreateTieFlags::empty())
+            .tie(&mapped, CreateTieFlags::empty());
+        assert_eq!(spec.object_create().lt, LifetimeType::Volatile);
+        // These are the ties passed to the kernel along with object_create().
+        assert_eq!(
+            spec.ties(),
+            &[
+                CreateTieSpec::new(unmapped, CreateTieFlags::empty()),
+                CreateTieSpec::new(mapped.id(), CreateTieFlags::empty()),
+            ]
+        );
+
+        let handle = create_spec(&spec).unwrap();
+        assert_ne!(handle.id(), unmapped);
+        assert!(handle.map_flags().contains(MapFlags::WRITE));
+    }
 }
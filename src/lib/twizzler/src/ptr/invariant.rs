@@ -1,7 +1,8 @@
 use std::marker::PhantomData;
 
-use twizzler_abi::object::MAX_SIZE;
-use twizzler_rt_abi::object::ObjectHandle;
+use thiserror::Error;
+use twizzler_abi::object::{MAX_SIZE, NULLPAGE_SIZE};
+use twizzler_rt_abi::object::{MapError, ObjectHandle};
 
 use super::{GlobalPtr, Ref};
 use crate::{
@@ -10,6 +11,23 @@ use crate::{
     tx::TxObject,
 };
 
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+/// Possible errors from resolving an invariant pointer.
+pub enum FotResolveError {
+    /// The pointer is null.
+    #[error("null pointer")]
+    Null,
+    /// The pointer is not stored inside an object, so it has no FOT to resolve against.
+    #[error("pointer is not stored in an object")]
+    NotInObject,
+    /// The FOT entry could not be resolved to an object.
+    #[error(transparent)]
+    Map(#[from] MapError),
+    /// The target does not fit within the resolved object's data, or is misaligned.
+    #[error("pointer target is out of bounds")]
+    OutOfBounds,
+}
+
 #[repr(C)]
 #[derive(Debug, PartialEq, PartialOrd, Ord, Eq)]
 pub struct InvPtr<T: Invariant> {
@@ -33,6 +51,13 @@ impl<T: Invariant> InvPtr<T> {
         GlobalPtr::new(re.id(), self.offset())
     }
 
+    /// Resolve the pointer, panicking if resolution fails.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, must be stored inside an object, and its FOT entry must
+    /// resolve to an object that contains a valid T at the pointer's offset. Use
+    /// [InvPtr::try_resolve] for pointers that may not meet these conditions, such as those read
+    /// from persistent data that might be corrupt.
     pub unsafe fn resolve(&self) -> Ref<'_, T> {
         let fote = self.fot_index();
         let obj = Self::get_this(self);
@@ -52,10 +77,49 @@ impl<T: Invariant> InvPtr<T> {
         Ref::from_handle(re, ptr)
     }
 
+    /// Resolve the pointer, checking each step. The object containing this pointer, the FOT
+    /// entry, and the target's bounds and alignment are all validated, and failures are returned
+    /// instead of panicking. The target must lie in the object's data, after the null page and
+    /// before the meta page.
+    ///
+    /// # Safety
+    /// Resolution checks that a T fits at the target, not that the bytes there are a valid T. This
+    /// cannot be a safe function, because many Invariant types (bool, Option, Result, and structs
+    /// containing them) have bit patterns that are not valid values, and the target's bytes may
+    /// be anything. The caller must ensure that the target holds a valid T.
+    pub unsafe fn try_resolve(&self) -> Result<Ref<'_, T>, FotResolveError> {
+        if self.is_null() {
+            return Err(FotResolveError::Null);
+        }
+        let offset = self.offset() as usize;
+        let end = offset
+            .checked_add(size_of::<T>())
+            .ok_or(FotResolveError::OutOfBounds)?;
+        if offset < NULLPAGE_SIZE || end > MAX_SIZE - NULLPAGE_SIZE || offset % align_of::<T>() != 0
+        {
+            return Err(FotResolveError::OutOfBounds);
+        }
+        let obj = twizzler_rt_abi::object::twz_rt_get_object_handle((self as *const Self).cast())
+            .ok_or(FotResolveError::NotInObject)?;
+        let target = match self.fot_index() {
+            0 => obj,
+            fote => twizzler_rt_abi::object::twz_rt_resolve_fot(&obj, fote, MAX_SIZE)?,
+        };
+        let ptr = target
+            .lea(offset, size_of::<T>())
+            .ok_or(FotResolveError::OutOfBounds)?
+            .cast();
+        Ok(Ref::from_handle(target, ptr))
+    }
+
     pub fn null() -> Self {
         Self::from_raw_parts(0, 0)
     }
 
+    pub fn is_null(&self) -> bool {
+        self.value == 0
+    }
+
     pub fn from_raw_parts(idx: u64, offset: u64) -> Self {
         Self {
             value: (idx << 48) | offset,
@@ -85,3 +149,63 @@ impl<T: Invariant> InvPtr<T> {
         Ok(Self::from_raw_parts(fote, gp.offset()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use twizzler_abi::object::{MAX_SIZE, NULLPAGE_SIZE};
+
+    use super::{FotResolveError, InvPtr};
+    use crate::{
+        marker::BaseType,
+        object::{ObjectBuilder, TypedObject},
+    };
+
+    #[repr(C)]
+    struct Foo {
+        ptrs: [InvPtr<u32>; 5],
+        val: u32,
+    }
+    impl BaseType for Foo {}
+
+    #[test]
+    fn try_resolve_bounds() {
+        let obj = ObjectBuilder::<Foo>::default()
+            .build_inplace(|tx| {
+                let ptrs = [
+                    InvPtr::null(),
+                    InvPtr::from_raw_parts(0, 8),
+                    InvPtr::from_raw_parts(0, (MAX_SIZE - NULLPAGE_SIZE) as u64),
+                    InvPtr::from_raw_parts(0, NULLPAGE_SIZE as u64 + 1),
+                    InvPtr::from_raw_parts(
+                        0,
+                        (NULLPAGE_SIZE + size_of::<[InvPtr<u32>; 5]>()) as u64,
+                    ),
+                ];
+                tx.write(Foo { ptrs, val: 42 })
+            })
+            .unwrap();
+        let base = obj.base();
+        unsafe {
+            assert_eq!(
+                base.ptrs[0].try_resolve().err(),
+                Some(FotResolveError::Null)
+            );
+            // Inside the null page.
+            assert_eq!(
+                base.ptrs[1].try_resolve().err(),
+                Some(FotResolveError::OutOfBounds)
+            );
+            // Inside the meta page.
+            assert_eq!(
+                base.ptrs[2].try_resolve().err(),
+                Some(FotResolveError::OutOfBounds)
+            );
+            // Misaligned.
+            assert_eq!(
+                base.ptrs[3].try_resolve().err(),
+                Some(FotResolveError::OutOfBounds)
+            );
+            assert_eq!(*base.ptrs[4].try_resolve().unwrap(), 42);
+        }
+    }
+}
@@ -11,12 +11,14 @@
 use std::{
     alloc::Layout,
     cell::UnsafeCell,
+    collections::{HashMap, VecDeque},
     marker::{PhantomData, Tuple},
     ptr::NonNull,
     sync::{
-        atomic::{AtomicPtr, AtomicU32, Ordering},
-        OnceLock,
+        atomic::{AtomicPtr, AtomicU32, AtomicU64, Ordering},
+        Mutex, OnceLock,
     },
+    time::Duration,
 };
 
 pub use dynlink::{
@@ -27,7 +29,10 @@ use secgate::{
     util::{Descriptor, Handle},
     Crossing, DynamicSecGate,
 };
-use twizzler_abi::object::{ObjID, MAX_SIZE, NULLPAGE_SIZE};
+use twizzler_abi::{
+    object::{ObjID, MAX_SIZE, NULLPAGE_SIZE},
+    syscall::{sys_object_create, BackingType, LifetimeType, ObjectCreate, ObjectCreateFlags},
+};
 
 #[allow(unused_imports, unused_variables, unexpected_cfgs)]
 mod gates {
@@ -35,7 +40,10 @@ mod gates {
 }
 
 pub use gates::*;
-use twizzler_rt_abi::debug::{DlPhdrInfo, LoadedImageId};
+use twizzler_rt_abi::{
+    debug::{DlPhdrInfo, LoadedImageId},
+    object::{MapFlags, ObjectHandle},
+};
 
 /// Shared data between the monitor and a compartment runtime. Written to by the monitor, and
 /// read-only from the compartment.
@@ -277,9 +285,19 @@ impl<'a> LibraryLoader<'a> {
 /// A compartment handle. On drop, the compartment may be unloaded.
 pub struct CompartmentHandle {
     desc: Option<Descriptor>,
+    // Gate addresses by name. Gate addresses are assumed stable for the lifetime of a compartment
+    // instance, so this is only cleared when the handle starts referring to a new instance.
+    gate_cache: Mutex<HashMap<String, usize>>,
 }
 
 impl CompartmentHandle {
+    fn new(desc: Option<Descriptor>) -> Self {
+        Self {
+            desc,
+            gate_cache: Mutex::new(HashMap::new()),
+        }
+    }
+
     /// Get the compartment info.
     pub fn info(&self) -> CompartmentInfo<'_> {
         CompartmentInfo::from_raw(
@@ -295,12 +313,21 @@ impl CompartmentHandle {
         self.desc
     }
 
+    /// Look up a secure gate in this compartment by name. Addresses are cached per handle, on the
+    /// assumption that a compartment's gates do not move while it is running.
     pub unsafe fn dynamic_gate<A: Tuple + Crossing + Copy, R: Crossing + Copy>(
         &self,
         name: &str,
     ) -> Option<DynamicSecGate<'_, A, R>> {
-        let name_len = lazy_sb::write_bytes_to_sb(name.as_bytes());
+        if let Some(address) = self.gate_cache.lock().unwrap().get(name) {
+            return Some(DynamicSecGate::new(*address));
+        }
+        let name_len = lazy_sb::write_all_bytes_to_sb(name.as_bytes())?;
         let address = gates::monitor_rt_compartment_dynamic_gate(self.desc, name_len).ok()??;
+        self.gate_cache
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), address);
         Some(DynamicSecGate::new(address))
     }
 }
@@ -311,6 +338,7 @@ pub struct CompartmentLoader {
     args: Vec<String>,
     env: Option<Vec<String>>,
     flags: NewCompartmentFlags,
+    capture: bool,
 }
 
 impl CompartmentLoader {
@@ -325,6 +353,7 @@ impl CompartmentLoader {
             flags,
             env: None,
             args: vec![],
+            capture: false,
         }
     }
 
@@ -342,6 +371,15 @@ impl CompartmentLoader {
         self
     }
 
+    /// Capture the compartment's stdout and stderr in new [OutputBuffer]s, instead of sending
+    /// them to the kernel console. The buffers can be found with [CompartmentHandle::stdout] and
+    /// [CompartmentHandle::stderr]. The monitor creates them tied to the compartment instance, so
+    /// they are deleted along with it.
+    pub fn capture_output(&mut self) -> &mut Self {
+        self.capture = true;
+        self
+    }
+
     /// Load the compartment.
     pub fn load(&self) -> Result<CompartmentHandle, gates::LoadCompartmentError> {
         fn get_current_env() -> Vec<String> {
@@ -354,7 +392,19 @@ impl CompartmentLoader {
             .args
             .iter()
             .fold(0, |acc, arg| acc + arg.as_bytes().len() + 1);
-        let env = self.env.clone().unwrap_or_else(|| get_current_env());
+        let mut env = self.env.clone().unwrap_or_else(|| get_current_env());
+        // Output capture is passed through the environment. It is not inherited from this
+        // compartment, whose output buffers belong to it. To request capture, the variables are
+        // passed with no value, and the monitor fills in new buffers.
+        env.retain(|var| {
+            !var.starts_with(&format!("{}=", STDOUT_CAPTURE_ENV))
+                && !var.starts_with(&format!("{}=", STDERR_CAPTURE_ENV))
+        });
+        if self.capture {
+            for var in [STDOUT_CAPTURE_ENV, STDERR_CAPTURE_ENV] {
+                env.push(format!("{}=", var));
+            }
+        }
         let envs_len = env
             .iter()
             .fold(0, |acc, arg| acc + arg.as_bytes().len() + 1);
@@ -367,8 +417,7 @@ impl CompartmentLoader {
             bytes.extend_from_slice(env.as_bytes());
             bytes.push(0);
         }
-        let len = lazy_sb::write_bytes_to_sb(&bytes);
-        if len < envs_len + args_len + name_len {
+        if lazy_sb::write_all_bytes_to_sb(&bytes).is_none() {
             return Err(gates::LoadCompartmentError::Unknown);
         }
         let desc = gates::monitor_rt_load_compartment(
@@ -380,7 +429,7 @@ impl CompartmentLoader {
         .ok()
         .ok_or(gates::LoadCompartmentError::Unknown)
         .flatten()?;
-        Ok(CompartmentHandle { desc: Some(desc) })
+        Ok(CompartmentHandle::new(Some(desc)))
     }
 }
 
@@ -397,7 +446,7 @@ impl Handle for CompartmentHandle {
             .ok()
             .flatten()
             .ok_or(())?;
-        Ok(CompartmentHandle { desc: Some(desc) })
+        Ok(CompartmentHandle::new(Some(desc)))
     }
 
     fn release(&mut self) {
@@ -440,6 +489,160 @@ impl Drop for LibraryHandle {
     }
 }
 
+/// The environment variable that names the object a compartment's stdout is captured to, as a hex
+/// object ID. Set by the monitor for compartments loaded with [CompartmentLoader::capture_output].
+pub const STDOUT_CAPTURE_ENV: &str = "TWZ_STDOUT_CAPTURE";
+/// Like [STDOUT_CAPTURE_ENV], for stderr.
+pub const STDERR_CAPTURE_ENV: &str = "TWZ_STDERR_CAPTURE";
+
+/// Possible errors from finding a compartment's captured output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The compartment was not loaded with [CompartmentLoader::capture_output].
+    NotCaptured,
+    /// The compartment's environment could not be read.
+    Unknown,
+}
+
+impl std::error::Error for CaptureError {}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        <Self as std::fmt::Debug>::fmt(self, f)
+    }
+}
+
+/// Parse the value of [STDOUT_CAPTURE_ENV] or [STDERR_CAPTURE_ENV].
+pub fn parse_capture_var(val: &str) -> Option<ObjID> {
+    u128::from_str_radix(val, 16).ok().map(ObjID::new)
+}
+
+#[repr(C)]
+struct OutputBufferHdr {
+    // Bytes claimed by writers, some of which may still be being copied in.
+    reserved: AtomicU64,
+    // Bytes that are fully written, unless a stalled writer was skipped. Always at most reserved.
+    len: AtomicU64,
+}
+
+/// An object that captured output is appended to. Any number of threads may append at once, and
+/// anyone may read what has been written so far. Output past the buffer's capacity is dropped.
+pub struct OutputBuffer {
+    handle: ObjectHandle,
+}
+
+// Safety: the header is only accessed atomically, and each writer copies only into the range it
+// reserved. The bytes before len are only written again by a writer that stalled past its turn to
+// publish, and readers copy bytes out rather than holding references to them.
+unsafe impl Send for OutputBuffer {}
+unsafe impl Sync for OutputBuffer {}
+
+impl OutputBuffer {
+    /// Create a new, empty output buffer in a new volatile object.
+    pub fn create() -> Option<Self> {
+        let id = sys_object_create(
+            ObjectCreate::new(
+                BackingType::Normal,
+                LifetimeType::Volatile,
+                None,
+                ObjectCreateFlags::empty(),
+            ),
+            &[],
+            &[],
+        )
+        .ok()?;
+        Self::open(id)
+    }
+
+    /// Open the output buffer in object `id`.
+    pub fn open(id: ObjID) -> Option<Self> {
+        let handle =
+            twizzler_rt_abi::object::twz_rt_map_object(id, MapFlags::READ | MapFlags::WRITE)
+                .ok()?;
+        Some(Self { handle })
+    }
+
+    /// Get the ID of the underlying object.
+    pub fn id(&self) -> ObjID {
+        self.handle.id()
+    }
+
+    fn hdr(&self) -> &OutputBufferHdr {
+        unsafe {
+            self.handle
+                .start()
+                .add(NULLPAGE_SIZE)
+                .cast::<OutputBufferHdr>()
+                .as_ref()
+                .unwrap()
+        }
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe {
+            self.handle
+                .start()
+                .add(NULLPAGE_SIZE + core::mem::size_of::<OutputBufferHdr>())
+        }
+    }
+
+    /// The most bytes the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        MAX_SIZE - NULLPAGE_SIZE * 2 - core::mem::size_of::<OutputBufferHdr>()
+    }
+
+    /// The number of bytes written so far.
+    pub fn len(&self) -> usize {
+        (self.hdr().len.load(Ordering::Acquire) as usize).min(self.capacity())
+    }
+
+    /// Whether nothing has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read written bytes starting at `pos` into `buf`, returning the number of bytes read.
+    pub fn read_at(&self, pos: usize, buf: &mut [u8]) -> usize {
+        let len = self.len().saturating_sub(pos).min(buf.len());
+        // Safety: these bytes lie within the object, and are copied out rather than referenced.
+        let src = unsafe { core::slice::from_raw_parts(self.data().add(pos), len) };
+        buf[..len].copy_from_slice(src);
+        len
+    }
+
+    /// Append `buf` to the buffer. Returns the length of `buf` even if some of it was dropped for
+    /// lack of space, so that writers never see an error for output that nobody may read.
+    ///
+    /// Concurrent appends do not interleave. Each writer reserves its range before copying, and
+    /// publishes it once all earlier ranges have been published. A writer that dies or stalls
+    /// mid-append must not wedge every later writer, so a writer that waits too long publishes its
+    /// range anyway, and readers may see the stalled writer's range incomplete.
+    pub fn append(&self, buf: &[u8]) -> usize {
+        const MAX_PUBLISH_WAITS: usize = 1000;
+        let hdr = self.hdr();
+        let pos = hdr.reserved.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        let end = pos + buf.len() as u64;
+        let start = (pos as usize).min(self.capacity());
+        let len = buf.len().min(self.capacity() - start);
+        unsafe {
+            core::ptr::copy_nonoverlapping(buf.as_ptr(), self.data().add(start), len);
+        }
+        for _ in 0..MAX_PUBLISH_WAITS {
+            match hdr
+                .len
+                .compare_exchange_weak(pos, end, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return buf.len(),
+                // A later writer gave up waiting and published past this range already.
+                Err(cur) if cur >= end => return buf.len(),
+                Err(_) => std::thread::yield_now(),
+            }
+        }
+        hdr.len.fetch_max(end, Ordering::Release);
+        buf.len()
+    }
+}
+
 /// Information about a compartment.
 #[derive(Clone, Debug)]
 pub struct CompartmentInfo<'a> {
@@ -472,19 +675,54 @@ impl<'a> CompartmentInfo<'a> {
 impl CompartmentHandle {
     /// Get a handle to the current compartment.
     pub fn current() -> Self {
-        Self { desc: None }
+        Self::new(None)
     }
 
     /// Lookup a compartment by name.
     pub fn lookup(name: impl AsRef<str>) -> Option<Self> {
-        let name_len = lazy_sb::write_bytes_to_sb(name.as_ref().as_bytes());
-        Some(Self {
-            desc: Some(
-                gates::monitor_rt_lookup_compartment(name_len)
-                    .ok()
-                    .flatten()?,
-            ),
-        })
+        let name_len = lazy_sb::write_all_bytes_to_sb(name.as_ref().as_bytes())?;
+        Some(Self::new(Some(
+            gates::monitor_rt_lookup_compartment(name_len)
+                .ok()
+                .flatten()?,
+        )))
+    }
+
+    /// Get the environment this compartment was launched with, as KEY=VALUE strings. This is a
+    /// copy kept by the monitor, so changes the compartment makes to its own environment after
+    /// launch are not reflected. Returns None if the environment could not be read.
+    pub fn env(&self) -> Option<Vec<String>> {
+        let len = gates::monitor_rt_get_compartment_env(self.desc)
+            .ok()
+            .flatten()?;
+        let bytes = lazy_sb::read_bytes_from_sb(len);
+        let (count, vars) = bytes.split_at_checked(core::mem::size_of::<u64>())?;
+        let count = u64::from_ne_bytes(count.try_into().ok()?) as usize;
+        let env: Vec<_> = vars
+            .split_terminator(|b| *b == 0)
+            .map(|var| String::from_utf8_lossy(var).into_owned())
+            .collect();
+        (env.len() == count).then_some(env)
+    }
+
+    /// Get the object that this compartment's stdout is captured to, if it was loaded with
+    /// [CompartmentLoader::capture_output]. Open it with [OutputBuffer::open] to read the output.
+    pub fn stdout(&self) -> Result<ObjID, CaptureError> {
+        self.captured(STDOUT_CAPTURE_ENV)
+    }
+
+    /// Get the object that this compartment's stderr is captured to. See
+    /// [CompartmentHandle::stdout].
+    pub fn stderr(&self) -> Result<ObjID, CaptureError> {
+        self.captured(STDERR_CAPTURE_ENV)
+    }
+
+    fn captured(&self, var: &str) -> Result<ObjID, CaptureError> {
+        self.env()
+            .ok_or(CaptureError::Unknown)?
+            .iter()
+            .find_map(|env| parse_capture_var(env.strip_prefix(var)?.strip_prefix('=')?))
+            .ok_or(CaptureError::NotCaptured)
     }
 
     /// Get an iterator over this compartment's dependencies.
@@ -492,6 +730,60 @@ impl CompartmentHandle {
         CompartmentDepsIter::new(self)
     }
 
+    /// Get the full, transitive dependency graph of this compartment. This compartment is node 0.
+    /// Each compartment appears once, even if several compartments depend on it or the
+    /// dependencies form a cycle.
+    pub fn dependency_graph(&self) -> DepGraph {
+        fn visit(
+            graph: &mut DepGraph,
+            index: &mut HashMap<ObjID, usize>,
+            queue: &mut VecDeque<(usize, CompartmentHandle)>,
+            from: usize,
+            comp: &CompartmentHandle,
+        ) {
+            for dep in comp.deps() {
+                let info = dep.info();
+                let to = match index.get(&info.id) {
+                    Some(to) => *to,
+                    None => {
+                        let to = graph.nodes.len();
+                        index.insert(info.id, to);
+                        graph.nodes.push(DepNode {
+                            id: info.id,
+                            name: info.name,
+                        });
+                        queue.push_back((to, dep));
+                        to
+                    }
+                };
+                if !graph.edges.contains(&(from, to)) {
+                    graph.edges.push((from, to));
+                }
+            }
+        }
+
+        let mut graph = DepGraph::default();
+        let mut index = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        let info = self.info();
+        index.insert(info.id, 0);
+        graph.nodes.push(DepNode {
+            id: info.id,
+            name: info.name,
+        });
+        visit(&mut graph, &mut index, &mut queue, 0, self);
+        while let Some((from, comp)) = queue.pop_front() {
+            visit(&mut graph, &mut index, &mut queue, from, &comp);
+        }
+        graph
+    }
+
+    /// Get an iterator over the threads running in this compartment.
+    pub fn threads(&self) -> CompartmentThreadsIter<'_> {
+        CompartmentThreadsIter::new(self)
+    }
+
     /// Get the root library for this compartment.
     pub fn root(&self) -> LibraryHandle {
         self.libs().next().unwrap()
@@ -502,6 +794,21 @@ impl CompartmentHandle {
         LibraryIter::new(self)
     }
 
+    /// Restart this compartment, loading a new instance from the same root library, arguments, and
+    /// environment. The compartment must have exited, and this must be the only handle to it.
+    /// Blocks until the new instance is ready, after which this handle refers to the new
+    /// instance. On error, this handle still refers to the old instance.
+    pub fn restart(&self) -> Result<(), gates::LoadCompartmentError> {
+        let desc = self.desc.ok_or(gates::LoadCompartmentError::Unknown)?;
+        gates::monitor_rt_compartment_restart(desc)
+            .ok()
+            .ok_or(gates::LoadCompartmentError::Unknown)
+            .flatten()?;
+        // The old instance is gone, and so are its gates.
+        self.gate_cache.lock().unwrap().clear();
+        Ok(())
+    }
+
     pub fn wait(&self, flags: CompartmentFlags) -> CompartmentFlags {
         CompartmentFlags::from_bits_truncate(
             gates::monitor_rt_compartment_wait(self.desc(), flags.bits())
@@ -509,6 +816,21 @@ impl CompartmentHandle {
                 .unwrap_or(0),
         )
     }
+
+    /// Like [Self::wait], but gives up after `timeout`. Returns Ok with the new flags if they
+    /// changed from `flags`, or Err with the current flags if the timeout expired first. If the
+    /// monitor could not be asked, no change was seen, so this returns Err with `flags`.
+    pub fn wait_timeout(
+        &self,
+        flags: CompartmentFlags,
+        timeout: Duration,
+    ) -> Result<CompartmentFlags, CompartmentFlags> {
+        match gates::monitor_rt_compartment_wait_timeout(self.desc(), flags.bits(), timeout).ok() {
+            Some(Ok(new)) => Ok(CompartmentFlags::from_bits_truncate(new)),
+            Some(Err(cur)) => Err(CompartmentFlags::from_bits_truncate(cur)),
+            None => Err(flags),
+        }
+    }
 }
 
 /// An iterator over libraries in a compartment.
@@ -555,7 +877,7 @@ impl<'a> Iterator for CompartmentDepsIter<'a> {
             .ok()
             .flatten()?;
         self.n += 1;
-        Some(CompartmentHandle { desc: Some(desc) })
+        Some(CompartmentHandle::new(Some(desc)))
     }
 
     fn nth(&mut self, n: usize) -> Option<Self::Item> {
@@ -564,6 +886,59 @@ impl<'a> Iterator for CompartmentDepsIter<'a> {
     }
 }
 
+/// A compartment in a [DepGraph].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepNode {
+    /// The instance ID of the compartment.
+    pub id: ObjID,
+    /// The name of the compartment.
+    pub name: String,
+}
+
+/// A compartment dependency graph, as returned by [CompartmentHandle::dependency_graph].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DepGraph {
+    /// The compartments in the graph, in breadth-first order from the root.
+    pub nodes: Vec<DepNode>,
+    /// Dependency edges, as (dependent, dependency) pairs of indices into nodes.
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl DepGraph {
+    /// Get the indices of the nodes that directly depend on `node`.
+    pub fn dependents(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges
+            .iter()
+            .filter(move |(_, to)| *to == node)
+            .map(|(from, _)| *from)
+    }
+}
+
+/// An iterator over the threads running in a compartment, ordered by thread ID. Threads that
+/// start or exit while iterating may be skipped or reported twice.
+pub struct CompartmentThreadsIter<'a> {
+    n: usize,
+    comp: &'a CompartmentHandle,
+}
+
+impl<'a> CompartmentThreadsIter<'a> {
+    fn new(comp: &'a CompartmentHandle) -> Self {
+        Self { n: 0, comp }
+    }
+}
+
+impl<'a> Iterator for CompartmentThreadsIter<'a> {
+    type Item = ThreadInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let info = gates::monitor_rt_get_compartment_thread(self.comp.desc, self.n)
+            .ok()
+            .flatten()?;
+        self.n += 1;
+        Some(info)
+    }
+}
+
 bitflags::bitflags! {
     /// Compartment state flags.
     #[derive(Clone, Debug, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
@@ -610,9 +985,15 @@ mod lazy_sb {
     //! A per-thread per-compartment simple buffer used for transferring strings between
     //! compartments and the monitor. This is necessary because the monitor runs at too low of a
     //! level for us to use nice shared memory techniques. This is simpler and more secure.
+    //!
+    //! The buffer is backed by a whole object, so a single transfer can be at most
+    //! [MAX_LEN] bytes, which is the largest a single object can hold. There is no larger
+    //! buffer to grow into, so callers that must not be truncated should use
+    //! [write_all_bytes_to_sb], which fails instead of silently dropping data.
     use std::cell::{OnceCell, RefCell};
 
     use secgate::util::SimpleBuffer;
+    use twizzler_abi::object::{MAX_SIZE, NULLPAGE_SIZE};
     use twizzler_rt_abi::object::MapFlags;
 
     struct LazyThreadSimpleBuffer {
@@ -671,6 +1052,19 @@ mod lazy_sb {
     pub(super) fn write_bytes_to_sb(buf: &[u8]) -> usize {
         LAZY_SB.borrow_mut().write(buf)
     }
+
+    /// The maximum number of bytes that can be transferred through the buffer at once.
+    pub(super) const MAX_LEN: usize = MAX_SIZE - NULLPAGE_SIZE * 2;
+
+    /// Write all of `buf` to the buffer, returning the length written, or None if `buf` is longer
+    /// than [MAX_LEN].
+    pub(super) fn write_all_bytes_to_sb(buf: &[u8]) -> Option<usize> {
+        if buf.len() > MAX_LEN {
+            return None;
+        }
+        let len = write_bytes_to_sb(buf);
+        (len == buf.len()).then_some(len)
+    }
 }
 
 pub const THREAD_STARTED: u32 = 1;
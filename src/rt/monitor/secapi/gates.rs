@@ -1,7 +1,11 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    time::Duration,
+};
 
 use dynlink::context::NewCompartmentFlags;
 use secgate::{util::Descriptor, Crossing};
+use twizzler_abi::thread::ExecutionState;
 use twizzler_rt_abi::{
     debug::DlPhdrInfo,
     object::{MapError, ObjID},
@@ -149,6 +153,20 @@ pub fn monitor_rt_compartment_dynamic_gate(
     monitor.get_compartment_gate_address(caller, info.thread_id(), desc, name_len)
 }
 
+#[cfg_attr(feature = "secgate-impl", secgate::secure_gate(options(info)))]
+#[cfg_attr(
+    not(feature = "secgate-impl"),
+    secgate::secure_gate(options(info, api))
+)]
+pub fn monitor_rt_get_compartment_env(
+    info: &secgate::GateCallInfo,
+    desc: Option<Descriptor>,
+) -> Option<usize> {
+    let monitor = crate::mon::get_monitor();
+    let caller = info.source_context().unwrap_or(MONITOR_INSTANCE_ID);
+    monitor.get_compartment_env(caller, info.thread_id(), desc)
+}
+
 #[cfg_attr(feature = "secgate-impl", secgate::secure_gate(options(info)))]
 #[cfg_attr(
     not(feature = "secgate-impl"),
@@ -164,6 +182,33 @@ pub fn monitor_rt_get_compartment_deps(
     monitor.get_compartment_deps(caller, desc, dep_n)
 }
 
+/// Information about a thread running in a compartment.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ThreadInfo {
+    /// The ID of the thread's repr object.
+    pub id: ObjID,
+    /// The thread's execution state.
+    pub state: ExecutionState,
+    /// The CPU time the thread has used, cumulative since the thread started.
+    pub cpu_time: Duration,
+}
+
+#[cfg_attr(feature = "secgate-impl", secgate::secure_gate(options(info)))]
+#[cfg_attr(
+    not(feature = "secgate-impl"),
+    secgate::secure_gate(options(info, api))
+)]
+pub fn monitor_rt_get_compartment_thread(
+    info: &secgate::GateCallInfo,
+    desc: Option<Descriptor>,
+    thread_n: usize,
+) -> Option<ThreadInfo> {
+    let monitor = crate::mon::get_monitor();
+    let caller = info.source_context().unwrap_or(MONITOR_INSTANCE_ID);
+    monitor.get_compartment_thread(caller, desc, thread_n)
+}
+
 #[cfg_attr(feature = "secgate-impl", secgate::secure_gate(options(info)))]
 #[cfg_attr(
     not(feature = "secgate-impl"),
@@ -234,6 +279,36 @@ pub fn monitor_rt_compartment_wait(
     monitor.compartment_wait(caller, desc, flags)
 }
 
+#[cfg_attr(feature = "secgate-impl", secgate::secure_gate(options(info)))]
+#[cfg_attr(
+    not(feature = "secgate-impl"),
+    secgate::secure_gate(options(info, api))
+)]
+pub fn monitor_rt_compartment_wait_timeout(
+    info: &secgate::GateCallInfo,
+    desc: Option<Descriptor>,
+    flags: u64,
+    timeout: Duration,
+) -> Result<u64, u64> {
+    let monitor = crate::mon::get_monitor();
+    let caller = info.source_context().unwrap_or(MONITOR_INSTANCE_ID);
+    monitor.compartment_wait_timeout(caller, desc, flags, timeout)
+}
+
+#[cfg_attr(feature = "secgate-impl", secgate::secure_gate(options(info)))]
+#[cfg_attr(
+    not(feature = "secgate-impl"),
+    secgate::secure_gate(options(info, api))
+)]
+pub fn monitor_rt_compartment_restart(
+    info: &secgate::GateCallInfo,
+    desc: Descriptor,
+) -> Result<(), LoadCompartmentError> {
+    let monitor = crate::mon::get_monitor();
+    let caller = info.source_context().unwrap_or(MONITOR_INSTANCE_ID);
+    monitor.restart_compartment(caller, desc)
+}
+
 #[cfg_attr(feature = "secgate-impl", secgate::secure_gate(options(info)))]
 #[cfg_attr(
     not(feature = "secgate-impl"),
@@ -412,6 +487,7 @@ pub struct MonitorStats {
 #[repr(C)]
 pub struct SpaceStats {
     pub mapped: usize,
+    pub free_slots: usize,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, ffi::CStr};
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    time::{Duration, Instant},
+};
 
 use dynlink::{
     compartment::CompartmentId,
@@ -6,12 +10,15 @@ use dynlink::{
     library::UnloadedLibrary,
 };
 use happylock::ThreadKey;
-use monitor_api::MONITOR_INSTANCE_ID;
+use monitor_api::{MONITOR_INSTANCE_ID, STDERR_CAPTURE_ENV, STDOUT_CAPTURE_ENV};
 use secgate::util::Descriptor;
-use twizzler_abi::syscall::{sys_thread_sync, ThreadSync, ThreadSyncSleep};
+use twizzler_abi::syscall::{
+    sys_object_create, sys_thread_sync, BackingType, CreateTieFlags, CreateTieSpec, LifetimeType,
+    ObjectCreate, ObjectCreateFlags, ThreadSync, ThreadSyncSleep,
+};
 use twizzler_rt_abi::object::ObjID;
 
-use crate::gates::{CompartmentInfo, CompartmentMgrStats, LoadCompartmentError};
+use crate::gates::{CompartmentInfo, CompartmentMgrStats, LoadCompartmentError, ThreadInfo};
 
 mod compconfig;
 mod compthread;
@@ -86,6 +93,21 @@ impl CompartmentMgr {
         Some(rc)
     }
 
+    /// Retire the [RunComp] `old` in favor of `new`, which takes over its name. Returns the old
+    /// RunComp, which the caller must clean up.
+    pub fn replace(&mut self, old: ObjID, new: ObjID) -> Option<RunComp> {
+        if !self.instances.contains_key(&new) {
+            return None;
+        }
+        let old_rc = self.remove(old)?;
+        // Unwrap-Ok: we just checked that new is present.
+        let rc = self.instances.get_mut(&new).unwrap();
+        self.names.remove(&rc.name);
+        rc.name = old_rc.name.clone();
+        self.names.insert(rc.name.clone(), new);
+        Some(old_rc)
+    }
+
     /// Get the [RunComp] for the monitor.
     pub fn _get_monitor(&self) -> &RunComp {
         // Unwrap-Ok: this instance is always present.
@@ -194,6 +216,39 @@ impl CompartmentMgr {
     }
 }
 
+// Give each output capture variable in env a new output buffer. The buffers are tied to the
+// compartment instance, so they are deleted along with it. CompartmentLoader never passes on its
+// own capture variables, so any that arrive here are requests, which carry no value. When a
+// compartment restarts, its old buffers are replaced in the same way.
+fn fill_capture_env(instance: ObjID, env: &[&CStr]) -> Result<Vec<CString>, LoadCompartmentError> {
+    env.iter()
+        .map(|var| {
+            let name = var
+                .to_bytes()
+                .split(|b| *b == b'=')
+                .next()
+                .unwrap_or_default();
+            if name != STDOUT_CAPTURE_ENV.as_bytes() && name != STDERR_CAPTURE_ENV.as_bytes() {
+                return Ok((*var).to_owned());
+            }
+            let id = sys_object_create(
+                ObjectCreate::new(
+                    BackingType::Normal,
+                    LifetimeType::Volatile,
+                    None,
+                    ObjectCreateFlags::empty(),
+                ),
+                &[],
+                &[CreateTieSpec::new(instance, CreateTieFlags::empty())],
+            )
+            .map_err(|_| LoadCompartmentError::Unknown)?;
+            let mut filled = name.to_vec();
+            filled.extend_from_slice(format!("={:x}", id.raw()).as_bytes());
+            CString::new(filled).map_err(|_| LoadCompartmentError::Unknown)
+        })
+        .collect()
+}
+
 impl super::Monitor {
     /// Get CompartmentInfo for this caller. Note that this will write to the compartment-thread's
     /// simple buffer.
@@ -316,6 +371,75 @@ impl super::Monitor {
         self.load_compartment_flags(instance)
     }
 
+    /// Like [Self::compartment_wait], but gives up after `timeout`. Returns Ok with the new flags
+    /// if they changed, or Err with the current flags on timeout.
+    #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
+    pub fn compartment_wait_timeout(
+        &self,
+        caller: ObjID,
+        desc: Option<Descriptor>,
+        flags: u64,
+        timeout: Duration,
+    ) -> Result<u64, u64> {
+        let Some(instance) = ({
+            let comphandles = self._compartment_handles.write(ThreadKey::get().unwrap());
+            let comp_id = desc
+                .map(|comp| comphandles.lookup(caller, comp).map(|ch| ch.instance))
+                .unwrap_or(Some(caller));
+            comp_id
+        }) else {
+            return Ok(0);
+        };
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            let current = self.load_compartment_flags(instance);
+            if current != flags {
+                return Ok(current);
+            }
+            let remaining = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) if !remaining.is_zero() => Some(remaining),
+                    _ => return Err(current),
+                },
+                None => None,
+            };
+            self.wait_for_compartment_state_change_timeout(instance, flags, remaining);
+        }
+    }
+
+    /// Write a compartment's environment to the caller-thread's simple buffer, as a u64 count
+    /// followed by that many NUL-terminated KEY=VALUE strings. Returns the number of bytes written,
+    /// or None if the compartment is unknown or the environment does not fit in the buffer.
+    /// Compartments that were not started by the loader report an empty environment.
+    #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
+    pub fn get_compartment_env(
+        &self,
+        instance: ObjID,
+        thread: ObjID,
+        desc: Option<Descriptor>,
+    ) -> Option<usize> {
+        let (ref mut space, _, ref mut comps, _, _, ref comphandles) =
+            *self.locks.lock(ThreadKey::get().unwrap());
+        let comp_id = desc
+            .map(|comp| comphandles.lookup(instance, comp).map(|ch| ch.instance))
+            .unwrap_or(Some(instance))?;
+
+        let env = comps
+            .get(comp_id)?
+            .launch
+            .as_ref()
+            .map(|launch| launch.env.clone())
+            .unwrap_or_default();
+        let mut bytes = (env.len() as u64).to_ne_bytes().to_vec();
+        for var in &env {
+            bytes.extend_from_slice(var.as_bytes_with_nul());
+        }
+
+        let pt = comps.get_mut(instance)?.get_per_thread(thread, space);
+        let len = pt.write_bytes(&bytes);
+        (len == bytes.len()).then_some(len)
+    }
+
     /// Open a handle to the n'th dependency compartment of a given compartment.
     #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
     pub fn get_compartment_deps(
@@ -336,6 +460,21 @@ impl super::Monitor {
         self.get_compartment_handle(caller, dep)
     }
 
+    /// Get information about the n'th thread running in a given compartment.
+    #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
+    pub fn get_compartment_thread(
+        &self,
+        caller: ObjID,
+        desc: Option<Descriptor>,
+        thread_n: usize,
+    ) -> Option<ThreadInfo> {
+        let (_, ref tmgr, _, _, _, ref comphandles) = *self.locks.lock(ThreadKey::get().unwrap());
+        let comp_id = desc
+            .map(|comp| comphandles.lookup(caller, comp).map(|ch| ch.instance))
+            .unwrap_or(Some(caller))?;
+        tmgr.compartment_thread_info(comp_id, thread_n)
+    }
+
     /// Load a new compartment with a root library ID, and return a compartment handle.
     #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
     pub fn load_compartment(
@@ -359,7 +498,6 @@ impl super::Monitor {
         let mut split = input.split("::");
         let compname = split.next().ok_or(LoadCompartmentError::Unknown)?;
         let libname = split.next().ok_or(LoadCompartmentError::Unknown)?;
-        let root = UnloadedLibrary::new(libname);
 
         // parse args
         let args_bytes = arg_bytes.split_inclusive(|b| *b == 0);
@@ -377,6 +515,40 @@ impl super::Monitor {
             .map_err(|_| LoadCompartmentError::Unknown)?;
         tracing::trace!("load {}: env: {:?}", compname, env);
 
+        let root_comp = self.build_compartment(compname, libname, new_comp_flags)?;
+        let env = fill_capture_env(root_comp, &env)?;
+        let env = env.iter().map(|var| var.as_c_str()).collect::<Vec<_>>();
+        if let Some(rc) = self
+            .comp_mgr
+            .write(ThreadKey::get().unwrap())
+            .get_mut(root_comp)
+        {
+            rc.launch = Some(LaunchInfo {
+                libname: libname.to_string(),
+                args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+                env: env.iter().map(|var| (*var).to_owned()).collect(),
+                flags: new_comp_flags,
+            });
+        }
+
+        let desc = self
+            .get_compartment_handle(caller, root_comp)
+            .ok_or(LoadCompartmentError::Unknown)?;
+
+        self.start_compartment(root_comp, &args, &env)?;
+
+        Ok(desc)
+    }
+
+    // Load and relocate a compartment's libraries and build its runtime compartments, but do not
+    // start it.
+    fn build_compartment(
+        &self,
+        compname: &str,
+        libname: &str,
+        new_comp_flags: NewCompartmentFlags,
+    ) -> Result<ObjID, LoadCompartmentError> {
+        let root = UnloadedLibrary::new(libname);
         let loader = {
             let mut dynlink = self.dynlink.write(ThreadKey::get().unwrap());
             loader::RunCompLoader::new(*dynlink, compname, root, new_comp_flags)
@@ -400,13 +572,102 @@ impl super::Monitor {
                 .map_err(|_| LoadCompartmentError::Unknown)?
         };
 
-        let desc = self
-            .get_compartment_handle(caller, root_comp)
-            .ok_or(LoadCompartmentError::Unknown)?;
+        Ok(root_comp)
+    }
 
-        self.start_compartment(root_comp, &args, &env)?;
+    /// Restart an exited compartment from the same root library, arguments, and environment it
+    /// was loaded with. The caller's handle is updated to refer to the new instance, and this
+    /// returns once the new instance is ready. The restart is refused if anything else (another
+    /// handle, or a dependent compartment) still uses the old instance. If the new instance cannot
+    /// be built, the old one is left in place and the handle still refers to it.
+    #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
+    pub fn restart_compartment(
+        &self,
+        caller: ObjID,
+        desc: Descriptor,
+    ) -> Result<(), LoadCompartmentError> {
+        let (old, name, mut launch) = {
+            let (_, _, ref cmgr, _, _, ref comphandles) =
+                *self.locks.lock(ThreadKey::get().unwrap());
+            let old = comphandles
+                .lookup(caller, desc)
+                .ok_or(LoadCompartmentError::Unknown)?
+                .instance;
+            let rc = cmgr.get(old).ok_or(LoadCompartmentError::Unknown)?;
+            Self::check_restartable(rc)?;
+            let launch = rc.launch.clone().ok_or(LoadCompartmentError::Unknown)?;
+            (old, rc.name.clone(), launch)
+        };
+        tracing::debug!("restarting compartment {} ({})", name, old);
+
+        let new = self.build_compartment(&name, &launch.libname, launch.flags)?;
+        let retire_new = |monitor: &Self| {
+            let (_, _, ref mut cmgr, ref mut dynlink, _, _) =
+                *monitor.locks.lock(ThreadKey::get().unwrap());
+            if let Some(rc) = cmgr.remove(new) {
+                cmgr.cleanup_queue.push(rc);
+            }
+            cmgr.process_cleanup_queue(&mut *dynlink);
+        };
+        let env = launch
+            .env
+            .iter()
+            .map(|var| var.as_c_str())
+            .collect::<Vec<_>>();
+        launch.env = match fill_capture_env(new, &env) {
+            Ok(env) => env,
+            Err(e) => {
+                retire_new(self);
+                return Err(e);
+            }
+        };
 
-        Ok(desc)
+        // Swap the new instance in for the old one, checking again that nothing else has started
+        // using the old one while the new one was built.
+        let swapped = {
+            let (_, _, ref mut cmgr, ref mut dynlink, _, ref mut comphandles) =
+                *self.locks.lock(ThreadKey::get().unwrap());
+            let mut swap = || {
+                Self::check_restartable(cmgr.get(old)?).ok()?;
+                let handle = comphandles.lookup_mut(caller, desc)?;
+                let old_rc = cmgr.replace(old, new)?;
+                handle.instance = new;
+                cmgr.cleanup_queue.push(old_rc);
+                let rc = cmgr.get_mut(new)?;
+                rc.inc_use_count();
+                rc.launch = Some(launch.clone());
+                Some(())
+            };
+            let swapped = swap().is_some();
+            cmgr.process_cleanup_queue(&mut *dynlink);
+            swapped
+        };
+        if !swapped {
+            retire_new(self);
+            return Err(LoadCompartmentError::Unknown);
+        }
+
+        let args = launch
+            .args
+            .iter()
+            .map(|arg| arg.as_c_str())
+            .collect::<Vec<_>>();
+        let env = launch
+            .env
+            .iter()
+            .map(|var| var.as_c_str())
+            .collect::<Vec<_>>();
+        self.start_compartment(new, &args, &env)
+    }
+
+    // Only an exited compartment that is used by nothing but the restarting handle can be
+    // restarted. We have no way to tear down a running compartment, and other users of the old
+    // instance would be left pointing at a removed one.
+    fn check_restartable(rc: &RunComp) -> Result<(), LoadCompartmentError> {
+        if !rc.has_flag(COMP_EXITED) || rc.use_count > 1 {
+            return Err(LoadCompartmentError::Unknown);
+        }
+        Ok(())
     }
 
     /// Drop a compartment handle.
@@ -440,6 +701,16 @@ impl super::Monitor {
 
     #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
     pub fn wait_for_compartment_state_change(&self, instance: ObjID, state: u64) {
+        self.wait_for_compartment_state_change_timeout(instance, state, None)
+    }
+
+    #[tracing::instrument(skip(self), level = tracing::Level::DEBUG)]
+    pub fn wait_for_compartment_state_change_timeout(
+        &self,
+        instance: ObjID,
+        state: u64,
+        timeout: Option<Duration>,
+    ) {
         let sl = {
             let cmp = self.comp_mgr.write(ThreadKey::get().unwrap());
             let Some(sl) = cmp.wait_for_compartment_state_change(instance, state) else {
@@ -453,7 +724,7 @@ impl super::Monitor {
             sl
         };
 
-        let _ = sys_thread_sync(&mut [ThreadSync::new_sleep(sl)], None);
+        let _ = sys_thread_sync(&mut [ThreadSync::new_sleep(sl)], timeout);
     }
 }
 
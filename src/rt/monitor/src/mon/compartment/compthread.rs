@@ -35,7 +35,13 @@ impl CompThread {
         };
         let mon = dynlink.get_compartment_mut(MONITOR_COMPARTMENT_ID).unwrap();
         let mt = tmgr
-            .start_thread(space, mon, Box::new(start), main_thread_comp)
+            .start_thread(
+                space,
+                mon,
+                Box::new(start),
+                main_thread_comp,
+                Some(instance),
+            )
             .into_diagnostic()?;
         Ok(Self {
             stack_object: stack,
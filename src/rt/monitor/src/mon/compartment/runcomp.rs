@@ -6,7 +6,10 @@ use std::{
     sync::atomic::{AtomicU64, Ordering},
 };
 
-use dynlink::{compartment::CompartmentId, context::Context};
+use dynlink::{
+    compartment::CompartmentId,
+    context::{Context, NewCompartmentFlags},
+};
 use monitor_api::{CompartmentFlags, RuntimeThreadControl, SharedCompConfig, TlsTemplateInfo};
 use secgate::util::SimpleBuffer;
 use talc::{ErrOnOom, Talc};
@@ -57,6 +60,20 @@ pub struct RunComp {
     per_thread: HashMap<ObjID, PerThread>,
     init_info: Option<(StackObject, usize, Vec<CtorSet>)>,
     pub(crate) use_count: u64,
+    pub(crate) launch: Option<LaunchInfo>,
+}
+
+/// The information used to load a compartment, kept so that it can be restarted.
+#[derive(Clone, Debug)]
+pub struct LaunchInfo {
+    /// The name of the root library.
+    pub libname: String,
+    /// Arguments passed to the compartment.
+    pub args: Vec<CString>,
+    /// Environment passed to the compartment.
+    pub env: Vec<CString>,
+    /// Flags used to create the compartment.
+    pub flags: NewCompartmentFlags,
 }
 
 impl Drop for RunComp {
@@ -161,6 +178,7 @@ impl RunComp {
             per_thread: HashMap::new(),
             init_info: Some((main_stack, entry, ctors.to_vec())),
             use_count: 0,
+            launch: None,
         }
     }
 
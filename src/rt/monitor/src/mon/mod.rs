@@ -152,13 +152,22 @@ impl Monitor {
     /// Start a managed monitor thread.
     #[tracing::instrument(skip(self, main), level = tracing::Level::DEBUG)]
     pub fn start_thread(&self, main: Box<dyn FnOnce()>) -> Result<ManagedThread, SpawnError> {
+        self.start_thread_in(None, main)
+    }
+
+    // Start a managed thread that will move itself into the given compartment.
+    fn start_thread_in(
+        &self,
+        comp: Option<ObjID>,
+        main: Box<dyn FnOnce()>,
+    ) -> Result<ManagedThread, SpawnError> {
         let key = ThreadKey::get().unwrap();
         let locks = &mut *self.locks.lock(key);
 
         let monitor_dynlink_comp = locks.3.get_compartment_mut(MONITOR_COMPARTMENT_ID).unwrap();
         locks
             .1
-            .start_thread(&mut locks.0, monitor_dynlink_comp, main, None)
+            .start_thread(&mut locks.0, monitor_dynlink_comp, main, None, comp)
     }
 
     /// Spawn a thread into a given compartment, using initial thread arguments.
@@ -170,17 +179,20 @@ impl Monitor {
         stack_ptr: usize,
         thread_ptr: usize,
     ) -> Result<ObjID, SpawnError> {
-        let thread = self.start_thread(Box::new(move || {
-            let frame = UpcallFrame::new_entry_frame(
-                stack_ptr,
-                args.stack_size,
-                thread_ptr,
-                instance,
-                args.start,
-                args.arg,
-            );
-            unsafe { twizzler_abi::syscall::sys_thread_resume_from_upcall(&frame) };
-        }))?;
+        let thread = self.start_thread_in(
+            Some(instance),
+            Box::new(move || {
+                let frame = UpcallFrame::new_entry_frame(
+                    stack_ptr,
+                    args.stack_size,
+                    thread_ptr,
+                    instance,
+                    args.start,
+                    args.arg,
+                );
+                unsafe { twizzler_abi::syscall::sys_thread_resume_from_upcall(&frame) };
+            }),
+        )?;
         Ok(thread.id)
     }
 
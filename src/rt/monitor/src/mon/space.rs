@@ -56,6 +56,48 @@ extern "C-unwind" {
     fn __monitor_get_slot_pair(one: *mut usize, two: *mut usize) -> bool;
     fn __monitor_release_pair(one: usize, two: usize);
     fn __monitor_release_slot(slot: usize);
+    fn __monitor_free_slot_count() -> usize;
+}
+
+/// A slot that has been reserved, but not yet mapped. The slot is released when the guard is
+/// dropped, unless it is consumed by [Space::map_reserved].
+pub struct SlotGuard {
+    slot: usize,
+}
+
+impl SlotGuard {
+    /// Get the reserved slot number.
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+
+    // Take the slot out of the guard, so that it is no longer released on drop.
+    fn into_slot(self) -> usize {
+        let slot = self.slot;
+        std::mem::forget(self);
+        slot
+    }
+}
+
+impl Drop for SlotGuard {
+    fn drop(&mut self) {
+        unsafe {
+            __monitor_release_slot(self.slot);
+        }
+    }
+}
+
+/// Reserve a slot for a future mapping, without mapping anything into it. Returns None if no slots
+/// are available.
+pub fn reserve_slot() -> Option<SlotGuard> {
+    let slot = unsafe { __monitor_get_slot() }.try_into().ok()?;
+    Some(SlotGuard { slot })
+}
+
+/// Get the number of slots that are currently free. This is only a snapshot; use [reserve_slot] to
+/// check for and claim capacity at the same time.
+pub fn free_slot_count() -> usize {
+    unsafe { __monitor_free_slot_count() }
 }
 
 impl Space {
@@ -63,36 +105,46 @@ impl Space {
     pub fn stat(&self) -> SpaceStats {
         SpaceStats {
             mapped: self.maps.len(),
+            free_slots: free_slot_count(),
         }
     }
 
     /// Map an object into the space.
     pub fn map(&mut self, info: MapInfo) -> Result<MapHandle, MapError> {
+        self.map_with(info, || reserve_slot().ok_or(MapError::OutOfResources))
+    }
+
+    /// Map an object into the space, using a previously reserved slot if the object is not already
+    /// mapped. If it is, the reserved slot is released.
+    pub fn map_reserved(&mut self, info: MapInfo, slot: SlotGuard) -> Result<MapHandle, MapError> {
+        self.map_with(info, || Ok(slot))
+    }
+
+    fn map_with(
+        &mut self,
+        info: MapInfo,
+        get_slot: impl FnOnce() -> Result<SlotGuard, MapError>,
+    ) -> Result<MapHandle, MapError> {
         // Can't use the entry API here because the closure may fail.
         let item = match self.maps.get_mut(&info) {
             Some(item) => item,
             None => {
-                // Not yet mapped, so allocate a slot and map it.
-                let slot = unsafe { __monitor_get_slot() }
-                    .try_into()
-                    .ok()
-                    .ok_or(MapError::OutOfResources)?;
+                // Not yet mapped, so allocate a slot and map it. The guard releases the slot if
+                // the map fails.
+                let slot = get_slot()?;
 
                 let Ok(_) = sys_object_map(
                     None,
                     info.id,
-                    slot,
+                    slot.slot(),
                     mapflags_into_prot(info.flags),
-                    twizzler_abi::syscall::MapFlags::empty(),
+                    info.flags.into(),
                 ) else {
-                    unsafe {
-                        __monitor_release_slot(slot);
-                    }
                     return Err(MapError::Other);
                 };
 
                 let map = MappedObject {
-                    addrs: MappedObjectAddrs::new(slot),
+                    addrs: MappedObjectAddrs::new(slot.into_slot()),
                     handle_count: 0,
                 };
                 self.maps.insert(info, map);
@@ -124,7 +176,7 @@ impl Space {
             info.id,
             one,
             mapflags_into_prot(info.flags),
-            twizzler_abi::syscall::MapFlags::empty(),
+            info.flags.into(),
         ) else {
             unsafe {
                 __monitor_release_pair(one, two);
@@ -137,7 +189,7 @@ impl Space {
             info2.id,
             two,
             mapflags_into_prot(info2.flags),
-            twizzler_abi::syscall::MapFlags::empty(),
+            info2.flags.into(),
         ) else {
             let _ = sys_object_unmap(None, one, UnmapFlags::empty())
                 .inspect_err(|e| tracing::warn!("failed to unmap first in pair on error: {}", e));
@@ -203,12 +255,19 @@ impl Space {
         ties: &[CreateTieSpec],
         map_flags: MapFlags,
     ) -> miette::Result<MapHandle> {
+        // Reserve the slot first, so we don't create an object we have no room to map.
+        let slot = reserve_slot()
+            .ok_or(MapError::OutOfResources)
+            .into_diagnostic()?;
         let id = sys_object_create(spec, sources, ties).into_diagnostic()?;
 
-        match self.map(MapInfo {
-            id,
-            flags: map_flags,
-        }) {
+        match self.map_reserved(
+            MapInfo {
+                id,
+                flags: map_flags,
+            },
+            slot,
+        ) {
             Ok(mh) => Ok(mh),
             Err(me) => {
                 if let Err(e) = sys_object_ctrl(id, ObjectControlCmd::Delete(DeleteFlags::empty()))
@@ -9,7 +9,10 @@ use dynlink::{compartment::Compartment, tls::TlsRegion};
 use monitor_api::{RuntimeThreadControl, MONITOR_INSTANCE_ID};
 use twizzler_abi::{
     object::NULLPAGE_SIZE,
-    syscall::{sys_spawn, sys_thread_exit, ThreadSyncSleep, UpcallTargetSpawnOption},
+    syscall::{
+        sys_spawn, sys_thread_exit, sys_thread_get_cpu_time, ThreadSyncSleep,
+        UpcallTargetSpawnOption,
+    },
     thread::{ExecutionState, ThreadRepr},
     upcall::{UpcallFlags, UpcallInfo, UpcallMode, UpcallOptions, UpcallTarget},
 };
@@ -19,7 +22,7 @@ use twizzler_rt_abi::{
 };
 
 use super::space::{MapHandle, MapInfo, Space};
-use crate::gates::ThreadMgrStats;
+use crate::gates::{ThreadInfo, ThreadMgrStats};
 
 mod cleaner;
 pub(crate) use cleaner::ThreadCleaner;
@@ -103,6 +106,22 @@ impl ThreadMgr {
         }
     }
 
+    /// Get information about the n'th thread running in a compartment, ordered by thread ID.
+    pub fn compartment_thread_info(&self, comp: ObjID, n: usize) -> Option<ThreadInfo> {
+        let mut threads: Vec<_> = self
+            .all
+            .values()
+            .filter(|th| th.comp == Some(comp))
+            .collect();
+        threads.sort_unstable_by_key(|th| th.id);
+        let th = threads.get(n)?;
+        Some(ThreadInfo {
+            id: th.id,
+            state: th.repr.get_repr().get_state(),
+            cpu_time: sys_thread_get_cpu_time(Some(th.id)).unwrap_or_default(),
+        })
+    }
+
     unsafe fn spawn_thread(
         start: usize,
         super_stack_start: usize,
@@ -142,6 +161,7 @@ impl ThreadMgr {
         start: unsafe extern "C" fn(usize) -> !,
         arg: usize,
         main_thread_comp: Option<ObjID>,
+        comp: Option<ObjID>,
     ) -> Result<ManagedThread, SpawnError> {
         let super_tls = monitor_dynlink_comp
             .build_tls_region(RuntimeThreadControl::default(), |layout| unsafe {
@@ -176,17 +196,20 @@ impl ThreadMgr {
             _super_stack: super_stack,
             _super_tls: super_tls,
             main_thread_comp,
+            comp,
         }))
     }
 
     /// Start a thread, running the provided Box'd closure. The thread will be running in
-    /// monitor-mode, and will have no connection to any compartment.
+    /// monitor-mode. If `comp` is set, the closure is expected to move the thread into that
+    /// compartment, and the thread is reported as one of its threads.
     pub fn start_thread(
         &mut self,
         space: &mut Space,
         monitor_dynlink_comp: &mut Compartment,
         main: Box<dyn FnOnce()>,
         main_thread_comp: Option<ObjID>,
+        comp: Option<ObjID>,
     ) -> Result<ManagedThread, SpawnError> {
         let main_addr = Box::into_raw(Box::new(main)) as usize;
         unsafe extern "C" fn managed_thread_entry(main: usize) -> ! {
@@ -204,6 +227,7 @@ impl ThreadMgr {
             managed_thread_entry,
             main_addr,
             main_thread_comp,
+            comp,
         );
         if let Ok(ref mt) = mt {
             if let Some(cleaner) = self.cleaner.get() {
@@ -224,6 +248,8 @@ pub struct ManagedThreadInner {
     _super_stack: Box<[MaybeUninit<u8>]>,
     _super_tls: TlsRegion,
     pub main_thread_comp: Option<ObjID>,
+    /// The compartment this thread runs in, if any.
+    pub comp: Option<ObjID>,
 }
 
 impl ManagedThreadInner {
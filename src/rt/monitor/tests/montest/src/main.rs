@@ -101,6 +101,226 @@ mod tests {
         let ret = unsafe { secgate::dynamic_gate_call(gate, (3,)).ok().unwrap() };
         assert_eq!(ret, 45);
     }
+
+    #[test]
+    fn test_dependency_graph() {
+        let current = CompartmentHandle::current();
+        let graph = current.dependency_graph();
+        assert_eq!(graph.nodes[0].id, current.info().id);
+
+        let name = format!("{}::libmontest_lib.so", current.info().name);
+        let lib = graph
+            .nodes
+            .iter()
+            .position(|node| node.name == name)
+            .expect("dependency not in graph");
+        assert!(graph.dependents(lib).any(|from| from == 0));
+
+        for (i, node) in graph.nodes.iter().enumerate() {
+            assert!(graph.nodes[(i + 1)..].iter().all(|n| n.id != node.id));
+        }
+        for (from, to) in &graph.edges {
+            assert!(*from < graph.nodes.len() && *to < graph.nodes.len());
+        }
+    }
+
+    // Run on its own by test_captured_output, in a compartment with captured output.
+    #[test]
+    fn print_capture_marker() {
+        println!("montest capture marker");
+    }
+
+    #[test]
+    fn test_captured_output() {
+        use monitor_api::{
+            CaptureError, CompartmentFlags, CompartmentLoader, NewCompartmentFlags, OutputBuffer,
+        };
+
+        let current = CompartmentHandle::current();
+        let root = current.root().info().name;
+        let bin = root.rsplit("::").next().unwrap();
+        let comp = CompartmentLoader::new("montest-capture", bin, NewCompartmentFlags::empty())
+            .args(&[
+                "montest",
+                "--test-threads=1",
+                "--nocapture",
+                "--exact",
+                "tests::print_capture_marker",
+            ])
+            .capture_output()
+            .load()
+            .unwrap();
+        let mut flags = comp.info().flags;
+        while !flags.contains(CompartmentFlags::EXITED) {
+            flags = comp.wait(flags);
+        }
+
+        let stdout = OutputBuffer::open(comp.stdout().unwrap()).unwrap();
+        let mut buf = vec![0; stdout.len()];
+        assert_eq!(stdout.read_at(0, &mut buf), buf.len());
+        let output = String::from_utf8_lossy(&buf);
+        assert!(output.lines().any(|line| line == "montest capture marker"));
+        assert!(comp.stderr().is_ok());
+        assert_eq!(current.stdout(), Err(CaptureError::NotCaptured));
+    }
+
+    #[test]
+    fn test_compartment_env() {
+        use monitor_api::{CompartmentFlags, CompartmentLoader, NewCompartmentFlags};
+
+        let current = CompartmentHandle::current();
+        let root = current.root().info().name;
+        let bin = root.rsplit("::").next().unwrap();
+        let comp = CompartmentLoader::new("montest-env", bin, NewCompartmentFlags::empty())
+            .args(&[
+                "montest",
+                "--test-threads=1",
+                "--exact",
+                "tests::print_capture_marker",
+            ])
+            .env(vec!["MONTEST_ENV_CHECK=known value".to_string()])
+            .load()
+            .unwrap();
+        let mut flags = comp.info().flags;
+        while !flags.contains(CompartmentFlags::EXITED) {
+            flags = comp.wait(flags);
+        }
+
+        let env = comp.env().unwrap();
+        assert!(env.iter().any(|var| var == "MONTEST_ENV_CHECK=known value"));
+    }
+
+    #[test]
+    fn test_restart() {
+        use monitor_api::{CompartmentFlags, CompartmentLoader, NewCompartmentFlags, OutputBuffer};
+
+        fn wait_exited(comp: &CompartmentHandle) {
+            let mut flags = comp.info().flags;
+            while !flags.contains(CompartmentFlags::EXITED) {
+                flags = comp.wait(flags);
+            }
+        }
+
+        let current = CompartmentHandle::current();
+        let root = current.root().info().name;
+        let bin = root.rsplit("::").next().unwrap();
+        let comp = CompartmentLoader::new("montest-restart", bin, NewCompartmentFlags::empty())
+            .args(&[
+                "montest",
+                "--test-threads=1",
+                "--nocapture",
+                "--exact",
+                "tests::print_capture_marker",
+            ])
+            .capture_output()
+            .load()
+            .unwrap();
+        wait_exited(&comp);
+        let old = comp.info();
+
+        // Another handle still uses the old instance, so the restart is refused and leaves the
+        // compartment as it was.
+        let other = CompartmentHandle::lookup(&old.name).unwrap();
+        assert!(comp.restart().is_err());
+        assert_eq!(comp.info().id, old.id);
+        drop(other);
+
+        comp.restart().unwrap();
+        let new = comp.info();
+        assert_ne!(new.id, old.id);
+        assert_eq!(new.name, old.name);
+        wait_exited(&comp);
+
+        let stdout = OutputBuffer::open(comp.stdout().unwrap()).unwrap();
+        let mut buf = vec![0; stdout.len()];
+        assert_eq!(stdout.read_at(0, &mut buf), buf.len());
+        let output = String::from_utf8_lossy(&buf);
+        assert_eq!(
+            output
+                .lines()
+                .filter(|line| *line == "montest capture marker")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_output_buffer_concurrent_append() {
+        use std::sync::Arc;
+
+        use monitor_api::OutputBuffer;
+
+        let buffer = Arc::new(OutputBuffer::create().unwrap());
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let buffer = buffer.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        buffer.append(format!("thread {} line {}\n", t, i).as_bytes());
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        let mut buf = vec![0; buffer.len()];
+        assert_eq!(buffer.read_at(0, &mut buf), buf.len());
+        let output = String::from_utf8(buf).unwrap();
+        let mut lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 400);
+        lines.sort();
+        lines.dedup();
+        assert_eq!(lines.len(), 400);
+        assert!(lines.iter().all(|line| line.starts_with("thread ")));
+    }
+
+    #[test]
+    fn test_compartment_threads() {
+        use std::sync::{mpsc, Arc, Barrier};
+
+        use twizzler_abi::{syscall::sys_thread_self_id, thread::ExecutionState};
+
+        let barrier = Arc::new(Barrier::new(3));
+        let (tx, rx) = mpsc::channel();
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let barrier = barrier.clone();
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    tx.send(sys_thread_self_id()).unwrap();
+                    barrier.wait();
+                })
+            })
+            .collect();
+        let ids: Vec<_> = rx.iter().take(2).collect();
+
+        let threads: Vec<_> = CompartmentHandle::current().threads().collect();
+        for id in &ids {
+            let info = threads
+                .iter()
+                .find(|th| th.id == *id)
+                .expect("spawned thread not reported");
+            assert_ne!(info.state, ExecutionState::Exited);
+        }
+
+        barrier.wait();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cpu_info() {
+        use twizzler_abi::syscall::{sys_cpu_info, sys_info, CpuInfo};
+
+        let mut cpus = [CpuInfo::default(); 256];
+        let count = sys_cpu_info(&mut cpus);
+        assert_eq!(count, sys_info().cpu_count().get());
+        let cpus = &cpus[..count.min(cpus.len())];
+        assert!(cpus.windows(2).all(|pair| pair[0].id < pair[1].id));
+    }
 }
 
 static WAS_CTOR_RUN: AtomicBool = AtomicBool::new(false);
@@ -18,7 +18,9 @@
 pub(crate) mod arch;
 
 mod runtime;
-pub use runtime::{set_upcall_handler, RuntimeState, OUR_RUNTIME};
+pub use runtime::{
+    set_upcall_handler, AllocStats, CpuTopology, FdReader, FdWriter, RuntimeState, OUR_RUNTIME,
+};
 
 mod error;
 pub use error::*;
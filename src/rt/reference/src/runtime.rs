@@ -5,6 +5,7 @@ use std::sync::atomic::{AtomicU32, Ordering};
 mod alloc;
 mod core;
 mod debug;
+mod fd_io;
 mod file;
 mod object;
 mod process;
@@ -13,9 +14,12 @@ mod thread;
 mod time;
 pub(crate) mod upcall;
 
+pub use fd_io::{FdReader, FdWriter};
+pub use thread::CpuTopology;
 use twizzler_abi::simple_mutex::Mutex;
 pub use upcall::set_upcall_handler;
 
+pub use self::alloc::AllocStats;
 use self::object::ObjectHandleManager;
 
 /// The runtime trait implementer itself.
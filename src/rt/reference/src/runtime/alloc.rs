@@ -41,6 +41,17 @@ impl ReferenceRuntime {
         &LOCAL_ALLOCATOR
     }
 
+    /// Get usage statistics for this compartment's allocator.
+    pub fn allocator_stats(&self) -> AllocStats {
+        LOCAL_ALLOCATOR.inner.lock().stats
+    }
+
+    /// Reset the allocator's peak usage to its current usage.
+    pub fn reset_allocator_peak(&self) {
+        let mut inner = LOCAL_ALLOCATOR.inner.lock();
+        inner.stats.peak_allocated_bytes = inner.stats.allocated_bytes;
+    }
+
     pub(crate) fn register_bootstrap_alloc(&self, slot: usize) {
         LOCAL_ALLOCATOR
             .bootstrap_alloc_slot
@@ -68,13 +79,44 @@ impl LocalAllocator {
     }
 }
 
+/// Usage statistics for a compartment's allocator. Byte counts are of requested sizes, after
+/// alignment is bumped to the allocator's minimum, and do not include allocator overhead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Bytes currently allocated.
+    pub allocated_bytes: usize,
+    /// The most bytes allocated at once since the allocator started, or since the peak was last
+    /// reset.
+    pub peak_allocated_bytes: usize,
+    /// Bytes of heap objects claimed by the allocator.
+    pub resident_bytes: usize,
+    /// The number of allocations made.
+    pub num_allocations: u64,
+    /// The number of allocations freed.
+    pub num_frees: u64,
+}
+
+impl AllocStats {
+    const fn new() -> Self {
+        Self {
+            allocated_bytes: 0,
+            peak_allocated_bytes: 0,
+            resident_bytes: 0,
+            num_allocations: 0,
+            num_frees: 0,
+        }
+    }
+}
+
 struct LocalAllocatorInner {
     talc: Talc<RuntimeOom>,
+    stats: AllocStats,
 }
 
 struct RuntimeOom {
     list_obj: Option<(usize, ObjID)>,
     objects: Vec<(usize, ObjID), FailAlloc>,
+    claimed_bytes: usize,
 }
 
 fn release_object(id: ObjID) {
@@ -154,6 +196,7 @@ impl OomHandler for RuntimeOom {
         }
 
         talc.oom_handler.objects.push((slot, id));
+        talc.oom_handler.claimed_bytes += top - base;
 
         Ok(())
     }
@@ -212,15 +255,25 @@ impl LocalAllocatorInner {
             talc: Talc::new(RuntimeOom {
                 objects: Vec::new_in(FailAlloc),
                 list_obj: None,
+                claimed_bytes: 0,
             }),
+            stats: AllocStats::new(),
         }
     }
 
     unsafe fn do_alloc(&mut self, layout: Layout) -> *mut u8 {
-        self.talc.malloc(layout).unwrap().as_ptr()
+        let ptr = self.talc.malloc(layout).unwrap().as_ptr();
+        let stats = &mut self.stats;
+        stats.allocated_bytes += layout.size();
+        stats.peak_allocated_bytes = stats.peak_allocated_bytes.max(stats.allocated_bytes);
+        stats.resident_bytes = self.talc.oom_handler.claimed_bytes;
+        stats.num_allocations += 1;
+        ptr
     }
 
     unsafe fn do_dealloc(&mut self, ptr: *mut u8, layout: Layout) {
         self.talc.free(NonNull::new(ptr).unwrap(), layout);
+        self.stats.allocated_bytes = self.stats.allocated_bytes.saturating_sub(layout.size());
+        self.stats.num_frees += 1;
     }
 }
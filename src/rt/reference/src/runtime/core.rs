@@ -144,6 +144,7 @@ impl ReferenceRuntime {
             None
         } else {
             unsafe { self.set_runtime_ready() };
+            self.init_stdio_capture();
             let ret = match monitor_api::monitor_rt_comp_ctrl(
                 monitor_api::MonitorCompControlCmd::RuntimeReady,
             ) {
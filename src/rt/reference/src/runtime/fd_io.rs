@@ -0,0 +1,199 @@
+//! Buffered [std::io] adapters over runtime file descriptors.
+
+use std::io::{self, ErrorKind, Read, Seek, Write};
+
+use twizzler_abi::object::NULLPAGE_SIZE;
+use twizzler_rt_abi::{
+    fd::RawFd,
+    io::{IoError, SeekFrom},
+};
+
+use super::OUR_RUNTIME;
+
+// Buffer a page at a time, so that small reads and writes turn into page-sized runtime calls.
+const BUFFER_SIZE: usize = NULLPAGE_SIZE;
+
+fn into_io_error(e: IoError) -> io::Error {
+    match e {
+        IoError::InvalidDesc => io::Error::from(ErrorKind::InvalidInput),
+        IoError::SeekError => io::Error::from(ErrorKind::InvalidInput),
+        _ => io::Error::from(ErrorKind::Other),
+    }
+}
+
+// Seek the descriptor's cursor. SeekFrom::End is resolved against the file length here, since the
+// runtime counts End offsets backwards from the end of the file.
+fn seek_fd(fd: RawFd, pos: io::SeekFrom) -> io::Result<u64> {
+    let pos = match pos {
+        io::SeekFrom::Start(x) => SeekFrom::Start(x),
+        io::SeekFrom::Current(x) => SeekFrom::Current(x),
+        io::SeekFrom::End(x) => {
+            let len = OUR_RUNTIME.fd_len(fd).map_err(into_io_error)?;
+            let pos = len
+                .checked_add_signed(x)
+                .ok_or_else(|| io::Error::from(ErrorKind::InvalidInput))?;
+            SeekFrom::Start(pos)
+        }
+    };
+    OUR_RUNTIME
+        .seek(fd, pos)
+        .map(|pos| pos as u64)
+        .map_err(into_io_error)
+}
+
+/// A buffered reader over a runtime file descriptor. Reads are served from an internal buffer that
+/// is refilled a page at a time. The descriptor is not closed when the reader is dropped.
+pub struct FdReader {
+    fd: RawFd,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl FdReader {
+    /// Create a new reader over `fd`, starting at the descriptor's current position.
+    pub fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Get the underlying file descriptor.
+    pub fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    /// Consume the reader, returning the underlying file descriptor. Any buffered data is lost.
+    pub fn into_inner(self) -> RawFd {
+        self.fd
+    }
+
+    fn buffered(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl Read for FdReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Large reads skip the buffer entirely if there is nothing left in it.
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return OUR_RUNTIME.read(self.fd, buf).map_err(into_io_error);
+        }
+        if self.pos == self.filled {
+            self.filled = OUR_RUNTIME
+                .read(self.fd, &mut self.buf)
+                .map_err(into_io_error)?;
+            self.pos = 0;
+        }
+        let len = self.buffered().len().min(buf.len());
+        buf[..len].copy_from_slice(&self.buf[self.pos..(self.pos + len)]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+impl Seek for FdReader {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        // The descriptor's cursor is ahead of ours by whatever is still buffered.
+        let remaining = self.buffered().len() as i64;
+        if let io::SeekFrom::Current(off) = pos {
+            // Seeks that stay within the buffer keep it, and leave the descriptor's cursor alone.
+            if off >= -(self.pos as i64) && off <= remaining {
+                let fd_pos = seek_fd(self.fd, io::SeekFrom::Current(0))?;
+                self.pos = (self.pos as i64 + off) as usize;
+                return Ok((fd_pos as i64 - remaining + off) as u64);
+            }
+            let ret = seek_fd(self.fd, io::SeekFrom::Current(off - remaining))?;
+            self.discard_buffer();
+            return Ok(ret);
+        }
+        let ret = seek_fd(self.fd, pos)?;
+        self.discard_buffer();
+        Ok(ret)
+    }
+}
+
+/// A buffered writer over a runtime file descriptor. Writes are collected in an internal buffer
+/// and written out a page at a time. The buffer is flushed on seek and on drop, but errors from
+/// the flush on drop are ignored, so call [Write::flush] to observe them. The descriptor is not
+/// closed when the writer is dropped.
+pub struct FdWriter {
+    fd: RawFd,
+    buf: Vec<u8>,
+}
+
+impl FdWriter {
+    /// Create a new writer over `fd`, starting at the descriptor's current position.
+    pub fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            buf: Vec::with_capacity(BUFFER_SIZE),
+        }
+    }
+
+    /// Get the underlying file descriptor.
+    pub fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    /// Flush the buffer and return the underlying file descriptor.
+    pub fn into_inner(mut self) -> io::Result<RawFd> {
+        self.flush_buf()?;
+        Ok(self.fd)
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let ret = loop {
+            if written == self.buf.len() {
+                break Ok(());
+            }
+            match OUR_RUNTIME.write(self.fd, &self.buf[written..]) {
+                Ok(0) => break Err(io::Error::from(ErrorKind::WriteZero)),
+                Ok(len) => written += len,
+                Err(e) => break Err(into_io_error(e)),
+            }
+        };
+        self.buf.drain(..written);
+        ret
+    }
+}
+
+impl Write for FdWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() + buf.len() > BUFFER_SIZE {
+            self.flush_buf()?;
+        }
+        // Writes at least as large as the buffer go straight to the descriptor.
+        if buf.len() >= BUFFER_SIZE {
+            return OUR_RUNTIME.write(self.fd, buf).map_err(into_io_error);
+        }
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()
+    }
+}
+
+impl Seek for FdWriter {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.flush_buf()?;
+        seek_fd(self.fd, pos)
+    }
+}
+
+impl Drop for FdWriter {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
@@ -3,18 +3,19 @@ use core::{
     mem::size_of,
     num::NonZeroUsize,
 };
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use bitflags::bitflags;
 use lazy_static::lazy_static;
 use lru::LruCache;
+use monitor_api::{OutputBuffer, STDERR_CAPTURE_ENV, STDOUT_CAPTURE_ENV};
 use naming_core::dynamic::{dynamic_naming_factory, DynamicNamingHandle};
 use stable_vec::{self, StableVec};
 use twizzler_abi::{
     object::{ObjID, NULLPAGE_SIZE},
     syscall::{
         sys_object_create, BackingType, DeleteFlags, LifetimeType, ObjectControlCmd, ObjectCreate,
-        ObjectCreateFlags,
+        ObjectCreateFlags, ObjectSource,
     },
 };
 use twizzler_rt_abi::{
@@ -67,10 +68,35 @@ fn get_fd_slots() -> &'static Mutex<StableVec<FdKind>> {
     &FD_SLOTS
 }
 
+// Look up the file behind fd. Stdio descriptors have no position, so they are a SeekError.
+fn get_file_desc(fd: RawFd) -> Result<Arc<Mutex<FileDesc>>, IoError> {
+    let binding = get_fd_slots().lock().unwrap();
+    match binding
+        .get(fd.try_into().unwrap())
+        .ok_or(IoError::InvalidDesc)?
+    {
+        FdKind::File(file_desc) => Ok(file_desc.clone()),
+        FdKind::Stdio => Err(IoError::SeekError),
+    }
+}
+
 fn get_naming_handle() -> &'static Mutex<DynamicNamingHandle> {
     &HANDLE
 }
 
+// The buffers that stdout and stderr are captured to, if this compartment was loaded with output
+// capture. Set up once before main, and until then stdio goes to the kernel console.
+static STDIO_CAPTURE: OnceLock<[Option<OutputBuffer>; 2]> = OnceLock::new();
+
+fn captured_stdio(fd: RawFd) -> Option<&'static OutputBuffer> {
+    let capture = STDIO_CAPTURE.get()?;
+    match fd {
+        1 => capture[0].as_ref(),
+        2 => capture[1].as_ref(),
+        _ => None,
+    }
+}
+
 #[derive(Debug)]
 pub enum CreateOptions {
     UNEXPECTED,
@@ -106,7 +132,35 @@ impl From<u32> for OperationOptions {
     }
 }
 
+// Check the open flags, and get the flags to map the file's object with.
+fn open_map_flags(open_opt: &OperationOptions) -> Result<MapFlags, OpenError> {
+    if open_opt.contains(OperationOptions::OPEN_FLAG_TRUNCATE)
+        && !open_opt.contains(OperationOptions::OPEN_FLAG_WRITE)
+    {
+        return Err(OpenError::InvalidArgument);
+    }
+    match (
+        open_opt.contains(OperationOptions::OPEN_FLAG_READ),
+        open_opt.contains(OperationOptions::OPEN_FLAG_WRITE),
+    ) {
+        (true, true) => Ok(MapFlags::READ | MapFlags::WRITE),
+        (true, false) => Ok(MapFlags::READ),
+        (false, true) => Ok(MapFlags::WRITE),
+        (false, false) => Err(OpenError::InvalidArgument),
+    }
+}
+
 impl ReferenceRuntime {
+    /// Send stdout and stderr to the buffers named in the environment, if the loader asked for
+    /// output capture. See [monitor_api::CompartmentLoader::capture_output].
+    pub(crate) fn init_stdio_capture(&self) {
+        let open = |var| {
+            let id = monitor_api::parse_capture_var(&std::env::var(var).ok()?)?;
+            OutputBuffer::open(id)
+        };
+        let _ = STDIO_CAPTURE.set([open(STDOUT_CAPTURE_ENV), open(STDERR_CAPTURE_ENV)]);
+    }
+
     pub fn open(
         &self,
         path: &str,
@@ -115,26 +169,14 @@ impl ReferenceRuntime {
     ) -> Result<RawFd, OpenError> {
         let mut session = get_naming_handle().lock().unwrap();
 
-        if open_opt.contains(OperationOptions::OPEN_FLAG_TRUNCATE)
-            && !open_opt.contains(OperationOptions::OPEN_FLAG_WRITE)
-        {
-            return Err(OpenError::InvalidArgument);
-        }
+        // Check the flags before creating anything.
+        open_map_flags(&open_opt)?;
         let create = ObjectCreate::new(
             BackingType::Normal,
             LifetimeType::Volatile,
             None,
             ObjectCreateFlags::empty(),
         );
-        let flags = match (
-            open_opt.contains(OperationOptions::OPEN_FLAG_READ),
-            open_opt.contains(OperationOptions::OPEN_FLAG_WRITE),
-        ) {
-            (true, true) => MapFlags::READ | MapFlags::WRITE,
-            (true, false) => MapFlags::READ,
-            (false, true) => MapFlags::WRITE,
-            (false, false) => return Err(OpenError::InvalidArgument),
-        };
         let obj_id: ObjID = match create_opt {
             CreateOptions::UNEXPECTED => return Err(OpenError::InvalidArgument),
             CreateOptions::CreateKindExisting => session.get(path).map_err(|e| e.into())?.into(),
@@ -150,7 +192,20 @@ impl ReferenceRuntime {
                 .unwrap_or(sys_object_create(create, &[], &[]).map_err(|_| OpenError::Other)?),
         };
 
-        let handle = self.map_object(obj_id, flags).unwrap();
+        let fd = self.open_id(obj_id, open_opt)?;
+        session
+            .put(path, obj_id.raw())
+            .map_err(|_| OpenError::Other)?;
+        Ok(fd)
+    }
+
+    /// Open the file stored in object `id` directly, without looking up a name. The object must
+    /// already exist. If it does not yet hold a file, it is initialized as an empty one.
+    pub fn open_id(&self, id: ObjID, open_opt: OperationOptions) -> Result<RawFd, OpenError> {
+        let flags = open_map_flags(&open_opt)?;
+        let handle = self
+            .map_object(id, flags)
+            .map_err(|_| OpenError::LookupFail)?;
         let metadata_handle = unsafe {
             handle
                 .start()
@@ -166,12 +221,6 @@ impl ReferenceRuntime {
                 }
             };
         }
-        if open_opt.contains(OperationOptions::OPEN_FLAG_TRUNCATE) {
-            unsafe {
-                { *metadata_handle }.size = 0;
-            }
-        }
-
         let elem = FdKind::File(Arc::new(Mutex::new(FileDesc {
             pos: 0,
             handle,
@@ -189,10 +238,14 @@ impl ReferenceRuntime {
             binding.insert(fd, elem);
             fd
         };
-        session
-            .put(path, obj_id.raw())
-            .map_err(|_| OpenError::Other)?;
-
+        // Both of these look the descriptor up again.
+        drop(binding);
+        if open_opt.contains(OperationOptions::OPEN_FLAG_TRUNCATE) {
+            // Truncate like fd_set_len, which also zeroes the discarded bytes, since everything
+            // past the end of a file must read as zero.
+            self.fd_set_len(fd.try_into().unwrap(), 0)
+                .map_err(|_| OpenError::Other)?;
+        }
         if open_opt.contains(OperationOptions::OPEN_FLAG_TAIL) {
             self.seek(fd.try_into().unwrap(), SeekFrom::End(0))
                 .map_err(|_| OpenError::Other)?;
@@ -217,7 +270,15 @@ impl ReferenceRuntime {
         };
 
         let mut binding = file_desc.lock().unwrap();
+        let pos = binding.pos;
+        let bytes_read = self.read_at(&mut binding, pos, buf);
+        binding.pos += bytes_read as u64;
 
+        Ok(bytes_read)
+    }
+
+    // Read from the file starting at pos, without touching the file's cursor.
+    fn read_at(&self, binding: &mut FileDesc, mut pos: u64, buf: &mut [u8]) -> usize {
         let metadata_handle = unsafe {
             binding
                 .handle
@@ -228,14 +289,14 @@ impl ReferenceRuntime {
 
         let mut bytes_read = 0;
         while bytes_read < buf.len() {
-            if binding.pos > (unsafe { *metadata_handle }).size {
+            if pos > (unsafe { *metadata_handle }).size {
                 break;
             }
 
-            let available_bytes = (unsafe { *metadata_handle }).size - binding.pos;
+            let available_bytes = (unsafe { *metadata_handle }).size - pos;
 
-            let object_window: usize = ((binding.pos) / WRITABLE_BYTES) as usize;
-            let offset = (binding.pos) % WRITABLE_BYTES;
+            let object_window: usize = ((pos) / WRITABLE_BYTES) as usize;
+            let offset = (pos) % WRITABLE_BYTES;
 
             if object_window > OBJECT_COUNT || available_bytes == 0 {
                 break;
@@ -252,40 +313,31 @@ impl ReferenceRuntime {
                 (buf.len() - bytes_read) as u64,
             );
 
-            let object_ptr = if object_window == 0 {
-                binding.handle.start()
-            } else {
-                if let Some(new_handle) = binding.map.get(&object_window) {
-                    new_handle.start()
-                } else {
-                    let obj_id =
-                        ((unsafe { *metadata_handle }).direct)[(object_window - 1) as usize];
-                    let flags = MapFlags::READ | MapFlags::WRITE;
-                    let handle = self.map_object(obj_id, flags).unwrap();
-                    binding.map.put(object_window, handle.clone());
-                    handle.start()
-                }
-            };
-
-            unsafe {
-                buf.as_mut_ptr().offset(bytes_read as isize).copy_from(
-                    object_ptr.offset(
-                        NULLPAGE_SIZE as isize
-                            + size_of::<FileMetadata>() as isize
-                            + offset as isize,
-                    ),
-                    bytes_to_read as usize,
-                )
+            // Windows that were never written to have no backing object, and read as zeros.
+            match self.existing_window_start(binding, object_window) {
+                Some(object_ptr) => unsafe {
+                    buf.as_mut_ptr().offset(bytes_read as isize).copy_from(
+                        object_ptr.offset(
+                            NULLPAGE_SIZE as isize
+                                + size_of::<FileMetadata>() as isize
+                                + offset as isize,
+                        ),
+                        bytes_to_read as usize,
+                    )
+                },
+                None => buf[bytes_read..(bytes_read + bytes_to_read as usize)].fill(0),
             }
 
-            binding.pos += bytes_to_read;
+            pos += bytes_to_read;
 
             bytes_read += bytes_to_read as usize;
         }
 
-        Ok(bytes_read)
+        bytes_read
     }
 
+    /// Read from the file at offset `off`, or at the cursor if `off` is None. Positioned reads do
+    /// not move the cursor. Reading at an offset past the end of the file returns a SeekError.
     pub fn fd_pread(
         &self,
         fd: RawFd,
@@ -293,12 +345,26 @@ impl ReferenceRuntime {
         buf: &mut [u8],
         _flags: IoFlags,
     ) -> Result<usize, IoError> {
-        if off.is_some() {
+        let Some(off) = off else {
+            return self.read(fd, buf);
+        };
+        let file_desc = get_file_desc(fd)?;
+        let mut binding = file_desc.lock().unwrap();
+        let metadata_handle = unsafe {
+            &*binding
+                .handle
+                .start()
+                .offset(NULLPAGE_SIZE as isize)
+                .cast::<FileMetadata>()
+        };
+        if off > metadata_handle.size {
             return Err(IoError::SeekError);
         }
-        self.read(fd, buf)
+        Ok(self.read_at(&mut binding, off, buf))
     }
 
+    /// Write to the file at offset `off`, or at the cursor if `off` is None. Positioned writes do
+    /// not move the cursor. Writing at an offset past the end of the file extends it.
     pub fn fd_pwrite(
         &self,
         fd: RawFd,
@@ -306,10 +372,15 @@ impl ReferenceRuntime {
         buf: &[u8],
         _flags: IoFlags,
     ) -> Result<usize, IoError> {
-        if off.is_some() {
+        let Some(off) = off else {
+            return self.write(fd, buf);
+        };
+        let file_desc = get_file_desc(fd)?;
+        let mut binding = file_desc.lock().unwrap();
+        if off > MAX_FILE_SIZE {
             return Err(IoError::SeekError);
         }
-        self.write(fd, buf)
+        Ok(self.write_at(&mut binding, off, buf))
     }
 
     pub fn fd_pwritev(
@@ -414,6 +485,9 @@ impl ReferenceRuntime {
             .ok_or(IoError::InvalidDesc)?;
 
         let FdKind::File(file_desc) = &file_desc else {
+            if let Some(output) = captured_stdio(fd) {
+                return Ok(output.append(buf));
+            }
             // Just do basic stdio via kernel console
             twizzler_abi::syscall::sys_kernel_console_write(
                 buf,
@@ -423,6 +497,16 @@ impl ReferenceRuntime {
         };
 
         let mut binding = file_desc.lock().unwrap();
+        let pos = binding.pos;
+        let bytes_written = self.write_at(&mut binding, pos, buf);
+        binding.pos += bytes_written as u64;
+
+        Ok(bytes_written)
+    }
+
+    // Write to the file starting at pos, without touching the file's cursor. Extends the file if
+    // the write goes past the end.
+    fn write_at(&self, binding: &mut FileDesc, mut pos: u64, buf: &[u8]) -> usize {
         let metadata_handle = unsafe {
             binding
                 .handle
@@ -434,12 +518,12 @@ impl ReferenceRuntime {
         let mut bytes_written = 0;
         while bytes_written < buf.len() {
             // The available bytes for writing is the OBJECT_SIZE * OBJECT_COUNT
-            // The metadata fills some bytes, the rest is defined by binding.pos which overlays the
+            // The metadata fills some bytes, the rest is defined by pos which overlays the
             // rest of the object space
-            let available_bytes = MAX_FILE_SIZE - binding.pos;
+            let available_bytes = MAX_FILE_SIZE - pos;
 
-            let object_window: usize = (binding.pos / WRITABLE_BYTES) as usize;
-            let offset = binding.pos % WRITABLE_BYTES;
+            let object_window: usize = (pos / WRITABLE_BYTES) as usize;
+            let offset = pos % WRITABLE_BYTES;
 
             if object_window > OBJECT_COUNT || available_bytes == 0 {
                 break;
@@ -504,12 +588,277 @@ impl ReferenceRuntime {
                         (bytes_to_write) as usize,
                     )
             }
-            binding.pos += bytes_to_write as u64;
-            unsafe { ((*metadata_handle).size) = max(binding.pos, (*metadata_handle).size) };
+            pos += bytes_to_write as u64;
+            unsafe { ((*metadata_handle).size) = max(pos, (*metadata_handle).size) };
             bytes_written += bytes_to_write as usize;
         }
 
-        Ok(bytes_written)
+        bytes_written
+    }
+
+    // Get the start of the object backing a window of the file, mapping it if necessary. Returns
+    // None if no object has been created for this window yet.
+    fn existing_window_start(
+        &self,
+        binding: &mut FileDesc,
+        object_window: usize,
+    ) -> Option<*mut u8> {
+        if object_window == 0 {
+            return Some(binding.handle.start());
+        }
+        if let Some(handle) = binding.map.get(&object_window) {
+            return Some(handle.start());
+        }
+        let metadata_handle = unsafe {
+            &*binding
+                .handle
+                .start()
+                .offset(NULLPAGE_SIZE as isize)
+                .cast::<FileMetadata>()
+        };
+        let obj_id = metadata_handle.direct[object_window - 1];
+        if obj_id == 0.into() {
+            return None;
+        }
+        let handle = self
+            .map_object(obj_id, MapFlags::READ | MapFlags::WRITE)
+            .unwrap();
+        binding.map.put(object_window, handle.clone());
+        Some(handle.start())
+    }
+
+    /// Get the length of the file, in bytes.
+    pub fn fd_len(&self, fd: RawFd) -> Result<u64, IoError> {
+        let file_desc = get_file_desc(fd)?;
+        let binding = file_desc.lock().unwrap();
+        let metadata_handle = unsafe {
+            &*binding
+                .handle
+                .start()
+                .offset(NULLPAGE_SIZE as isize)
+                .cast::<FileMetadata>()
+        };
+        Ok(metadata_handle.size)
+    }
+
+    /// Set the length of the file, in bytes. Growing the file fills the new region with zeros, and
+    /// shrinking it discards the data past the new end. Lengths beyond the maximum file size (the
+    /// capacity of all of a file's objects) return a SeekError. The cursor is not moved.
+    pub fn fd_set_len(&self, fd: RawFd, len: u64) -> Result<(), IoError> {
+        if len > MAX_FILE_SIZE {
+            return Err(IoError::SeekError);
+        }
+        let file_desc = get_file_desc(fd)?;
+        let mut binding = file_desc.lock().unwrap();
+        let metadata_handle = unsafe {
+            binding
+                .handle
+                .start()
+                .offset(NULLPAGE_SIZE as isize)
+                .cast::<FileMetadata>()
+        };
+
+        // Everything past the end of the file is kept zeroed, so growing only needs to update
+        // the size, and shrinking needs to clear the discarded tail.
+        let old_len = unsafe { (*metadata_handle).size };
+        let mut pos = len;
+        while pos < old_len {
+            let object_window = (pos / WRITABLE_BYTES) as usize;
+            let offset = pos % WRITABLE_BYTES;
+            let bytes_to_zero = min(WRITABLE_BYTES - offset, old_len - pos);
+            if let Some(object_ptr) = self.existing_window_start(&mut binding, object_window) {
+                unsafe {
+                    object_ptr
+                        .offset(
+                            NULLPAGE_SIZE as isize
+                                + size_of::<FileMetadata>() as isize
+                                + offset as isize,
+                        )
+                        .write_bytes(0, bytes_to_zero as usize)
+                }
+            }
+            pos += bytes_to_zero;
+        }
+        unsafe { (*metadata_handle).size = len };
+
+        Ok(())
+    }
+
+    /// Copy up to `len` bytes from `src` at `src_off` to `dst` at `dst_off`, without moving either
+    /// cursor. Returns the number of bytes copied, which is short if the source file ends first or
+    /// the destination reaches the maximum file size. A `src_off` past the end of the source, or a
+    /// `dst_off` past the maximum file size, returns a SeekError. Overlapping ranges within the
+    /// same file are not supported, and return an Other error.
+    ///
+    /// Whole windows that line up in both files are shared copy-on-write by creating the
+    /// destination window from the source window's object. Everything else is copied through a
+    /// buffer.
+    pub fn copy_range(
+        &self,
+        src: RawFd,
+        dst: RawFd,
+        src_off: u64,
+        dst_off: u64,
+        len: u64,
+    ) -> Result<u64, IoError> {
+        let src_desc = get_file_desc(src)?;
+        let dst_desc = get_file_desc(dst)?;
+        if dst_off > MAX_FILE_SIZE {
+            return Err(IoError::SeekError);
+        }
+
+        if Arc::ptr_eq(&src_desc, &dst_desc) {
+            if src_off < dst_off.saturating_add(len) && dst_off < src_off.saturating_add(len) {
+                return Err(IoError::Other);
+            }
+            let mut binding = src_desc.lock().unwrap();
+            return self.copy_locked(&mut binding, None, src_off, dst_off, len);
+        }
+
+        // Always take the two locks in the same order, so concurrent copies in opposite directions
+        // cannot deadlock.
+        let (mut src_binding, mut dst_binding) = if Arc::as_ptr(&src_desc) < Arc::as_ptr(&dst_desc)
+        {
+            let src_binding = src_desc.lock().unwrap();
+            (src_binding, dst_desc.lock().unwrap())
+        } else {
+            let dst_binding = dst_desc.lock().unwrap();
+            (src_desc.lock().unwrap(), dst_binding)
+        };
+        self.copy_locked(
+            &mut src_binding,
+            Some(&mut dst_binding),
+            src_off,
+            dst_off,
+            len,
+        )
+    }
+
+    // Copy between two locked files. A dst of None means the copy is within src.
+    fn copy_locked(
+        &self,
+        src: &mut FileDesc,
+        mut dst: Option<&mut FileDesc>,
+        src_off: u64,
+        dst_off: u64,
+        len: u64,
+    ) -> Result<u64, IoError> {
+        const COPY_CHUNK: usize = 1 << 16;
+
+        let src_size = unsafe {
+            (*src
+                .handle
+                .start()
+                .offset(NULLPAGE_SIZE as isize)
+                .cast::<FileMetadata>())
+            .size
+        };
+        if src_off > src_size {
+            return Err(IoError::SeekError);
+        }
+        let len = min(
+            min(len, src_size - src_off),
+            MAX_FILE_SIZE.saturating_sub(dst_off),
+        );
+
+        let mut buf = vec![0u8; min(len, COPY_CHUNK as u64) as usize];
+        let mut copied = 0;
+        while copied < len {
+            let src_pos = src_off + copied;
+            let dst_pos = dst_off + copied;
+
+            if let Some(dst) = dst.as_deref_mut() {
+                if len - copied >= WRITABLE_BYTES && self.share_window(src, dst, src_pos, dst_pos) {
+                    copied += WRITABLE_BYTES;
+                    continue;
+                }
+            }
+
+            let chunk = min(len - copied, buf.len() as u64) as usize;
+            let read = self.read_at(src, src_pos, &mut buf[..chunk]);
+            if read == 0 {
+                break;
+            }
+            let dst = match dst.as_deref_mut() {
+                Some(dst) => dst,
+                None => &mut *src,
+            };
+            let written = self.write_at(dst, dst_pos, &buf[..read]);
+            copied += written as u64;
+            if written < read {
+                break;
+            }
+        }
+
+        Ok(copied)
+    }
+
+    // Copy a whole window from src to dst without touching its bytes, if both positions are the
+    // start of a window past the first, and the destination window has no object yet. The new
+    // destination object is a copy-on-write snapshot of the source window's object. Returns false
+    // if the window has to be copied through a buffer instead.
+    fn share_window(
+        &self,
+        src: &mut FileDesc,
+        dst: &mut FileDesc,
+        src_pos: u64,
+        dst_pos: u64,
+    ) -> bool {
+        if src_pos % WRITABLE_BYTES != 0 || dst_pos % WRITABLE_BYTES != 0 {
+            return false;
+        }
+        let src_window = (src_pos / WRITABLE_BYTES) as usize;
+        let dst_window = (dst_pos / WRITABLE_BYTES) as usize;
+        if src_window == 0
+            || dst_window == 0
+            || src_window > DIRECT_OBJECT_COUNT
+            || dst_window > DIRECT_OBJECT_COUNT
+        {
+            return false;
+        }
+
+        let src_metadata = unsafe {
+            &*src
+                .handle
+                .start()
+                .offset(NULLPAGE_SIZE as isize)
+                .cast::<FileMetadata>()
+        };
+        let dst_metadata = unsafe {
+            &mut *dst
+                .handle
+                .start()
+                .offset(NULLPAGE_SIZE as isize)
+                .cast::<FileMetadata>()
+        };
+        if dst_metadata.direct[dst_window - 1] != 0.into() {
+            return false;
+        }
+
+        // A source window with no object reads as zeros, and so does the destination window, so
+        // only the size needs to change.
+        let src_id = src_metadata.direct[src_window - 1];
+        if src_id != 0.into() {
+            let create = ObjectCreate::new(
+                BackingType::Normal,
+                LifetimeType::Volatile,
+                None,
+                ObjectCreateFlags::empty(),
+            );
+            let source = ObjectSource::new_copy(
+                src_id,
+                0,
+                0,
+                NULLPAGE_SIZE + size_of::<FileMetadata>() + WRITABLE_BYTES as usize,
+            );
+            let Ok(new_id) = sys_object_create(create, &[source], &[]) else {
+                return false;
+            };
+            dst_metadata.direct[dst_window - 1] = new_id;
+        }
+        dst_metadata.size = max(dst_metadata.size, dst_pos + WRITABLE_BYTES);
+
+        true
     }
 
     pub fn close(&self, fd: RawFd) -> Option<()> {
@@ -15,11 +15,17 @@ mod handlecache;
 #[repr(C)]
 pub(crate) struct RuntimeHandleInfo {
     refs: AtomicU64,
+    // Where the mapping was first made, for tracking down who holds on to mappings. Shared by all
+    // clones of the handle, since they share this info.
+    #[cfg(feature = "debug-tags")]
+    debug_tag: Option<&'static str>,
 }
 
 pub(crate) fn new_runtime_info() -> *mut RuntimeHandleInfo {
     let rhi = Box::new(RuntimeHandleInfo {
         refs: AtomicU64::new(1),
+        #[cfg(feature = "debug-tags")]
+        debug_tag: None,
     });
     Box::into_raw(rhi)
 }
@@ -45,11 +51,40 @@ pub(crate) fn new_object_handle(id: ObjID, slot: usize, flags: MapFlags) -> Obje
 }
 
 impl ReferenceRuntime {
+    /// Map an object. Mappings are cached by (id, flags), so mapping an object that is already
+    /// mapped with the same flags returns a new reference to the existing mapping. Released
+    /// mappings stay cached for a while before they are unmapped, so they can be reused too.
     #[tracing::instrument(ret, skip(self), level = "trace")]
     pub fn map_object(&self, id: ObjID, flags: MapFlags) -> Result<ObjectHandle, MapError> {
         self.object_manager
             .lock()
-            .map_object(ObjectMapKey(id.into(), flags))
+            .map_object(ObjectMapKey(id.into(), flags), None)
+    }
+
+    /// Map an object like [ReferenceRuntime::map_object], tagging the mapping with where it was
+    /// made. The tag is shared by all clones of the returned handle, and can be read back with
+    /// [ReferenceRuntime::debug_tag]. If the mapping is already cached, it keeps the tag of
+    /// whoever mapped it first. Without the debug-tags feature, the tag is ignored.
+    #[tracing::instrument(ret, skip(self), level = "trace")]
+    pub fn map_object_tagged(
+        &self,
+        id: ObjID,
+        flags: MapFlags,
+        tag: &'static str,
+    ) -> Result<ObjectHandle, MapError> {
+        self.object_manager
+            .lock()
+            .map_object(ObjectMapKey(id.into(), flags), Some(tag))
+    }
+
+    /// Get the tag that the mapping behind a handle was made with, if any. See
+    /// [ReferenceRuntime::map_object_tagged].
+    #[cfg(feature = "debug-tags")]
+    pub fn debug_tag(&self, handle: &ObjectHandle) -> Option<&'static str> {
+        let manager = self.object_manager.lock();
+        let raw = manager.cache.get_from_ptr(handle.start())?;
+        let info = unsafe { raw.runtime_info.cast::<RuntimeHandleInfo>().as_ref()? };
+        info.debug_tag
     }
 
     #[tracing::instrument(skip(self), level = "trace")]
@@ -137,8 +172,13 @@ impl ObjectHandleManager {
         }
     }
 
-    /// Map an object with this manager. Will call to monitor if needed.
-    pub fn map_object(&mut self, key: ObjectMapKey) -> Result<ObjectHandle, MapError> {
+    /// Map an object with this manager. Will call to monitor if needed. A new mapping is tagged
+    /// with `tag`, if debug tags are enabled.
+    pub fn map_object(
+        &mut self,
+        key: ObjectMapKey,
+        tag: Option<&'static str>,
+    ) -> Result<ObjectHandle, MapError> {
         if let Some(handle) = self.cache.activate(key) {
             let oh = ObjectHandle::from_raw(handle);
             let oh2 = oh.clone();
@@ -147,6 +187,12 @@ impl ObjectHandleManager {
         }
         let mapping = monitor_api::monitor_rt_object_map(key.0, key.1).unwrap()?;
         let handle = new_object_handle(key.0, mapping.slot, key.1).into_raw();
+        #[cfg(feature = "debug-tags")]
+        unsafe {
+            (*handle.runtime_info.cast::<RuntimeHandleInfo>()).debug_tag = tag;
+        }
+        #[cfg(not(feature = "debug-tags"))]
+        let _ = tag;
         self.cache.insert(handle);
         Ok(ObjectHandle::from_raw(handle))
     }
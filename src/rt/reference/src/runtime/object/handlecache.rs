@@ -63,6 +63,13 @@ impl HandleCache {
         self.activate(*map)
     }
 
+    /// Look up an active handle by a pointer into its slot, without changing its position in the
+    /// cache.
+    pub fn get_from_ptr(&self, ptr: *const u8) -> Option<&object_handle> {
+        let slot = (ptr as usize) / MAX_SIZE;
+        self.active.get(self.slotmap.get(&slot)?)
+    }
+
     /// Insert a handle into the active list. Item must not be already mapped.
     pub fn insert(&mut self, handle: object_handle) {
         let slot = (handle.start as usize) / MAX_SIZE;
@@ -106,6 +106,11 @@ impl SlotAllocatorInner {
         Some(pair.1)
     }
 
+    fn free_count(&self) -> usize {
+        let used_pairs: usize = self.pairs.iter().map(|b| b.count_ones() as usize).sum();
+        (SLOTS / 2 - used_pairs) * 2 + self.singles.len()
+    }
+
     fn release_single(&mut self, slot: usize) {
         self.singles.push(slot);
         self.maybe_gc_singles();
@@ -204,6 +209,16 @@ impl ReferenceRuntime {
         // early alloc has no ability to release slots
     }
 
+    /// Get the number of slots that are currently free for allocation. This is only a snapshot;
+    /// other threads may allocate or release slots at any time after it is taken.
+    pub fn free_slot_count(&self) -> usize {
+        if self.state().contains(RuntimeState::READY) {
+            SLOT_ALLOCATOR.inner.lock().unwrap().free_count()
+        } else {
+            SLOTS.saturating_sub(EARLY_SLOT_ALLOC.next.load(Ordering::SeqCst))
+        }
+    }
+
     /// Allocate a pair of adjacent slots, returning their numbers if a pair is available.
     /// The returned tuple will always be of form (x, x+1).
     pub fn allocate_pair(&self) -> Option<(usize, usize)> {
@@ -1,9 +1,13 @@
 //! Implements thread management routines.
 
 use dynlink::tls::Tcb;
-use twizzler_abi::syscall::{
-    sys_thread_sync, sys_thread_yield, ThreadSync, ThreadSyncError, ThreadSyncFlags, ThreadSyncOp,
-    ThreadSyncReference, ThreadSyncSleep, ThreadSyncWake,
+use twizzler_abi::{
+    object::ObjID,
+    syscall::{
+        sys_cpu_info, sys_thread_set_affinity, sys_thread_set_priority, sys_thread_sync,
+        sys_thread_yield, CpuInfo, ThreadPriority, ThreadSpawnError, ThreadSync, ThreadSyncError,
+        ThreadSyncFlags, ThreadSyncOp, ThreadSyncReference, ThreadSyncSleep, ThreadSyncWake,
+    },
 };
 use twizzler_rt_abi::thread::{JoinError, SpawnError, ThreadSpawnArgs, TlsIndex};
 
@@ -21,11 +25,60 @@ const MIN_STACK_ALIGN: usize = 128;
 
 static THREAD_MGR: ThreadManager = ThreadManager::new();
 
+/// The logical CPUs on the system, and how they are grouped into clusters.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CpuTopology {
+    cpus: Vec<CpuInfo>,
+}
+
+impl CpuTopology {
+    /// Get the number of logical CPUs.
+    pub fn cpu_count(&self) -> usize {
+        self.cpus.len()
+    }
+
+    /// Get all the CPUs, ordered by ID.
+    pub fn cpus(&self) -> &[CpuInfo] {
+        &self.cpus
+    }
+
+    /// Get the IDs of the clusters, in increasing order.
+    pub fn clusters(&self) -> Vec<u32> {
+        let mut clusters: Vec<_> = self.cpus.iter().map(|cpu| cpu.cluster).collect();
+        clusters.sort();
+        clusters.dedup();
+        clusters
+    }
+
+    /// Get the IDs of the CPUs in a cluster, which can be passed to
+    /// [ReferenceRuntime::set_affinity].
+    pub fn cluster_cpus(&self, cluster: u32) -> impl Iterator<Item = u32> + '_ {
+        self.cpus
+            .iter()
+            .filter(move |cpu| cpu.cluster == cluster)
+            .map(|cpu| cpu.id)
+    }
+}
+
 impl ReferenceRuntime {
     pub fn available_parallelism(&self) -> core::num::NonZeroUsize {
         twizzler_abi::syscall::sys_info().cpu_count()
     }
 
+    /// Get the CPU topology of the system. The number of CPUs matches
+    /// [Self::available_parallelism].
+    pub fn cpu_topology(&self) -> CpuTopology {
+        let mut cpus = vec![CpuInfo::default(); sys_cpu_info(&mut [])];
+        let count = sys_cpu_info(&mut cpus);
+        cpus.truncate(count);
+        if cpus.is_empty() {
+            // The kernel can't tell us, so assume every CPU is in a cluster of its own.
+            let count = self.available_parallelism().get() as u32;
+            cpus = (0..count).map(|id| CpuInfo { id, cluster: id }).collect();
+        }
+        CpuTopology { cpus }
+    }
+
     pub fn futex_wait(
         &self,
         futex: &core::sync::atomic::AtomicU32,
@@ -94,4 +147,41 @@ impl ReferenceRuntime {
     pub fn join(&self, id: u32, timeout: Option<std::time::Duration>) -> Result<(), JoinError> {
         self.impl_join(id, timeout)
     }
+
+    /// Restrict the thread to the given CPUs. An empty slice lets the thread run anywhere. The
+    /// kernel can only pin a thread to a single CPU, so sets of more than one CPU are rejected.
+    pub fn set_affinity(&self, id: u32, cpus: &[usize]) -> Result<(), SpawnError> {
+        let cpu = match cpus {
+            [] => None,
+            [cpu] => Some(u32::try_from(*cpu).map_err(|_| SpawnError::InvalidArgument)?),
+            _ => return Err(SpawnError::InvalidArgument),
+        };
+        let target = self.thread_repr_id(id)?;
+        sys_thread_set_affinity(target, cpu).map_err(spawn_error)
+    }
+
+    /// Change the scheduling priority of the thread.
+    pub fn set_priority(&self, id: u32, prio: ThreadPriority) -> Result<(), SpawnError> {
+        let target = self.thread_repr_id(id)?;
+        sys_thread_set_priority(target, prio).map_err(spawn_error)
+    }
+
+    // Resolve a runtime thread ID to the thread's repr object ID, or None for the calling thread.
+    fn thread_repr_id(&self, id: u32) -> Result<Option<ObjID>, SpawnError> {
+        if with_current_thread(|cur| cur.id()) == id {
+            return Ok(None);
+        }
+        THREAD_MGR
+            .with_internal(id, |th| Some(th.repr_handle().id()))
+            .ok_or(SpawnError::InvalidArgument)
+    }
+}
+
+fn spawn_error(e: ThreadSpawnError) -> SpawnError {
+    match e {
+        ThreadSpawnError::InvalidArgument
+        | ThreadSpawnError::NotFound
+        | ThreadSpawnError::PermissionDenied => SpawnError::InvalidArgument,
+        ThreadSpawnError::Unknown => SpawnError::KernelError,
+    }
 }
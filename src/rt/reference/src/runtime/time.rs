@@ -2,7 +2,7 @@
 
 use std::time::Duration;
 
-use twizzler_abi::syscall::{sys_read_clock_info, ClockSource, ReadClockFlags};
+use twizzler_abi::syscall::{sys_read_clock_info, ClockSource, ReadClockFlags, TimeSpan};
 use twizzler_rt_abi::time::Monotonicity;
 
 use super::ReferenceRuntime;
@@ -25,4 +25,22 @@ impl ReferenceRuntime {
             .expect("failed to get monotonic time from kernel");
         Duration::from(clock_info.current_value())
     }
+
+    /// The smallest increment the monotonic clock can report. A value of [Duration::ZERO] means
+    /// the resolution is unknown or finer than a nanosecond.
+    pub fn monotonic_resolution(&self) -> Duration {
+        clock_resolution(ClockSource::BestMonotonic)
+    }
+
+    /// The smallest increment the system clock can report. A value of [Duration::ZERO] means
+    /// the resolution is unknown or finer than a nanosecond.
+    pub fn system_resolution(&self) -> Duration {
+        clock_resolution(ClockSource::BestRealTime)
+    }
+}
+
+fn clock_resolution(source: ClockSource) -> Duration {
+    sys_read_clock_info(source, ReadClockFlags::empty())
+        .map(|info| TimeSpan::from_femtos(info.resolution().0).into())
+        .unwrap_or(Duration::ZERO)
 }
@@ -699,6 +699,11 @@ pub unsafe extern "C-unwind" fn __monitor_release_slot(slot: usize) {
     OUR_RUNTIME.release_slot(slot);
 }
 
+#[no_mangle]
+pub unsafe extern "C-unwind" fn __monitor_free_slot_count() -> usize {
+    OUR_RUNTIME.free_slot_count()
+}
+
 #[no_mangle]
 pub unsafe extern "C-unwind" fn __monitor_release_pair(one: usize, two: usize) {
     OUR_RUNTIME.release_pair((one, two));
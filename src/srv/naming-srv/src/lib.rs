@@ -5,7 +5,9 @@ use std::sync::Mutex;
 
 use lazy_init::LazyTransform;
 use lazy_static::lazy_static;
-use naming_core::{Entry, ErrorKind, NameSession, NameStore, Result};
+use naming_core::{
+    Entry, EntryName, ErrorKind, NameSession, NameStore, NamespaceSnapshot, PutFailure, Result,
+};
 use secgate::{
     secure_gate,
     util::{Descriptor, HandleMgr, SimpleBuffer},
@@ -18,6 +20,8 @@ use twizzler_rt_abi::object::{MapFlags, ObjID};
 struct NamespaceClient<'a> {
     session: NameSession<'a>,
     buffer: SimpleBuffer,
+    // The enumeration being paged out by enumerate_names_paged, and the name it was taken for.
+    snapshot: Option<(EntryName, NamespaceSnapshot)>,
 }
 
 impl<'a> NamespaceClient<'a> {
@@ -38,7 +42,11 @@ impl<'a> NamespaceClient<'a> {
             twizzler_rt_abi::object::twz_rt_map_object(id, MapFlags::WRITE | MapFlags::READ)
                 .ok()?;
         let buffer = SimpleBuffer::new(handle);
-        Some(Self { session, buffer })
+        Some(Self {
+            session,
+            buffer,
+            snapshot: None,
+        })
     }
 
     fn sbid(&self) -> ObjID {
@@ -123,6 +131,111 @@ pub fn put(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<()> {
     client.session.put(provided.name, provided.entry_type)
 }
 
+#[secure_gate(options(info))]
+pub fn put_many(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<usize> {
+    let service = NAMINGSERVICE.get().unwrap();
+    let mut binding = service.handles.lock().unwrap();
+    let client = binding
+        .lookup_mut(info.source_context().unwrap_or(0.into()), desc)
+        .ok_or(ErrorKind::Other)?;
+
+    // The batch is a u64 count followed by that many entries.
+    let mut count_buf = [0u8; std::mem::size_of::<u64>()];
+    client.buffer.read(&mut count_buf);
+    let count: usize = u64::from_ne_bytes(count_buf)
+        .try_into()
+        .map_err(|_| ErrorKind::Other)?;
+    if count > (client.buffer.max_len() - count_buf.len()) / std::mem::size_of::<Entry>() {
+        return Err(ErrorKind::Other);
+    }
+
+    let mut buf_vec = vec![0u8; count_buf.len() + count * std::mem::size_of::<Entry>()];
+    client.buffer.read(&mut buf_vec);
+    let provided: Vec<_> = (0..count)
+        .map(|i| {
+            let entry = unsafe {
+                (buf_vec.as_ptr().add(count_buf.len()) as *const Entry)
+                    .add(i)
+                    .read_unaligned()
+            };
+            (entry.name, entry.entry_type)
+        })
+        .collect();
+
+    let failures = client.session.put_many(&provided).err().unwrap_or_default();
+
+    let slice = unsafe {
+        std::slice::from_raw_parts(
+            failures.as_ptr() as *const u8,
+            failures.len() * std::mem::size_of::<PutFailure>(),
+        )
+    };
+    client.buffer.write(slice);
+
+    Ok(failures.len())
+}
+
+#[secure_gate(options(info))]
+pub fn link(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<()> {
+    let service = NAMINGSERVICE.get().unwrap();
+    let mut binding = service.handles.lock().unwrap();
+    let client = binding
+        .lookup_mut(info.source_context().unwrap_or(0.into()), desc)
+        .ok_or(ErrorKind::Other)?;
+
+    // The new name, followed by the existing one.
+    let mut buf = [0u8; 2 * std::mem::size_of::<Entry>()];
+    client.buffer.read(&mut buf);
+    let (name, existing) = unsafe {
+        let entries = buf.as_ptr() as *const Entry;
+        (entries.read_unaligned(), entries.add(1).read_unaligned())
+    };
+
+    client.session.link(name.name, existing.name)
+}
+
+#[secure_gate(options(info))]
+pub fn symlink(
+    info: &secgate::GateCallInfo,
+    desc: Descriptor,
+    name_len: usize,
+    target_len: usize,
+) -> Result<()> {
+    let service = NAMINGSERVICE.get().unwrap();
+    let mut binding = service.handles.lock().unwrap();
+    let client = binding
+        .lookup_mut(info.source_context().unwrap_or(0.into()), desc)
+        .ok_or(ErrorKind::Other)?;
+
+    // The name's bytes, followed by the target's.
+    let len = name_len
+        .checked_add(target_len)
+        .filter(|len| *len <= client.buffer.max_len())
+        .ok_or(ErrorKind::InvalidName)?;
+    let mut buf = vec![0u8; len];
+    client.buffer.read(&mut buf);
+    let (name, target) = buf.split_at(name_len);
+    let name = EntryName::from_bytes(name)?;
+    let target = EntryName::from_bytes(target)?;
+
+    client.session.symlink(name, target)
+}
+
+#[secure_gate(options(info))]
+pub fn get_no_follow(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<Entry> {
+    let service = NAMINGSERVICE.get().unwrap();
+    let mut binding = service.handles.lock().unwrap();
+    let client = binding
+        .lookup_mut(info.source_context().unwrap_or(0.into()), desc)
+        .ok_or(ErrorKind::Other)?;
+
+    let mut buf = [0u8; std::mem::size_of::<Entry>()];
+    client.buffer.read(&mut buf);
+    let provided = unsafe { std::mem::transmute::<[u8; std::mem::size_of::<Entry>()], Entry>(buf) };
+
+    client.session.get_no_follow(provided.name)
+}
+
 #[secure_gate(options(info))]
 pub fn get(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<Entry> {
     let service = NAMINGSERVICE.get().unwrap();
@@ -185,6 +298,51 @@ pub fn enumerate_names(info: &secgate::GateCallInfo, desc: Descriptor) -> Result
     Ok(len)
 }
 
+#[secure_gate(options(info))]
+pub fn enumerate_names_paged(
+    info: &secgate::GateCallInfo,
+    desc: Descriptor,
+    offset: usize,
+    max: usize,
+) -> Result<(usize, usize)> {
+    let service = NAMINGSERVICE.get().unwrap();
+    let mut binding = service.handles.lock().unwrap();
+    let client = binding
+        .lookup_mut(info.source_context().unwrap_or(0.into()), desc)
+        .ok_or(ErrorKind::Other)?;
+
+    let mut buf = [0u8; std::mem::size_of::<Entry>()];
+    client.buffer.read(&mut buf);
+    let provided = unsafe { std::mem::transmute::<[u8; std::mem::size_of::<Entry>()], Entry>(buf) };
+
+    // Never write more than fits in the buffer, regardless of what the client asked for.
+    let max = max.min(client.buffer.max_len() / std::mem::size_of::<Entry>());
+
+    // The first page takes a snapshot of the namespace, and later pages are served from it, so
+    // the store is only locked once per enumeration.
+    let snapshot = match client.snapshot.take() {
+        Some((name, snapshot)) if offset != 0 && name == provided.name => snapshot,
+        _ => client.session.enumerate_snapshot(provided.name)?,
+    };
+    let page = snapshot.page(offset, max);
+    let len = page.len();
+    let total = snapshot.len();
+
+    let slice = unsafe {
+        std::slice::from_raw_parts(
+            page.as_ptr() as *const u8,
+            len * std::mem::size_of::<Entry>(),
+        )
+    };
+    client.buffer.write(slice);
+
+    if offset + len < total {
+        client.snapshot = Some((provided.name, snapshot));
+    }
+
+    Ok((len, total))
+}
+
 #[secure_gate(options(info))]
 pub fn change_namespace(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<()> {
     let service = NAMINGSERVICE.get().unwrap();
@@ -197,5 +355,36 @@ pub fn change_namespace(info: &secgate::GateCallInfo, desc: Descriptor) -> Resul
     client.buffer.read(&mut buf);
     let provided = unsafe { std::mem::transmute::<[u8; std::mem::size_of::<Entry>()], Entry>(buf) };
 
+    // Relative names in a saved enumeration no longer mean the same thing.
+    client.snapshot = None;
     client.session.change_namespace(provided.name)
 }
+
+#[secure_gate(options(info))]
+pub fn cwd(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<usize> {
+    let service = NAMINGSERVICE.get().unwrap();
+    let mut binding = service.handles.lock().unwrap();
+    let client = binding
+        .lookup_mut(info.source_context().unwrap_or(0.into()), desc)
+        .ok_or(ErrorKind::Other)?;
+
+    let cwd = client.session.cwd();
+    let bytes = cwd.as_os_str().as_encoded_bytes();
+    if bytes.len() > client.buffer.max_len() {
+        return Err(ErrorKind::Other);
+    }
+    client.buffer.write(bytes);
+
+    Ok(bytes.len())
+}
+
+#[secure_gate(options(info))]
+pub fn flush_namespace(info: &secgate::GateCallInfo, desc: Descriptor) -> Result<()> {
+    let service = NAMINGSERVICE.get().unwrap();
+    let mut binding = service.handles.lock().unwrap();
+    let client = binding
+        .lookup_mut(info.source_context().unwrap_or(0.into()), desc)
+        .ok_or(ErrorKind::Other)?;
+
+    client.session.flush()
+}